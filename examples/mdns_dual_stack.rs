@@ -0,0 +1,31 @@
+#[cfg(windows)]
+fn main() {
+    eprintln!("Dual-stack sockets are only available on unix platforms");
+}
+
+#[cfg(not(windows))]
+fn main() {
+    use multicast_socket::DualStackMulticastSocket;
+    use std::net::{SocketAddrV4, SocketAddrV6};
+
+    let mdns_v4 = SocketAddrV4::new([224, 0, 0, 251].into(), 5353);
+    let mdns_v6 = SocketAddrV6::new([0xff02, 0, 0, 0, 0, 0, 0, 0xfb].into(), 5353, 0, 0);
+
+    let socket = DualStackMulticastSocket::all_interfaces(mdns_v4, mdns_v6)
+        .expect("could not create and bind socket");
+
+    let data = vec![1, 2];
+    socket
+        .broadcast(&data)
+        .expect("could not broadcast message to interfaces being listened");
+
+    loop {
+        if let Ok(message) = socket.receive() {
+            dbg!(&message.interface);
+            dbg!(&message.origin_address);
+
+            let data = vec![9, 8, 7];
+            socket.reply(&data, &message).expect("could not send data");
+        };
+    }
+}
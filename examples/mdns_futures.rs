@@ -0,0 +1,37 @@
+#[cfg(windows)]
+fn main() {
+    eprintln!("async-io sockets are only available on unix platforms");
+}
+
+#[cfg(not(windows))]
+fn main() {
+    use futures::{executor, SinkExt, StreamExt};
+    use multicast_socket::{Interface, MulticastFramed};
+    use std::net::SocketAddrV4;
+
+    let mdns_multicast_address = SocketAddrV4::new([224, 0, 0, 251].into(), 5353);
+
+    let mut socket = MulticastFramed::all_interfaces(mdns_multicast_address)
+        .expect("could not create and bind socket");
+
+    executor::block_on(async {
+        let data = vec![1, 2];
+        socket
+            .send((data, Interface::Default))
+            .await
+            .expect("could not send message");
+
+        while let Some(message) = socket.next().await {
+            if let Ok(message) = message {
+                dbg!(&message.interface);
+                dbg!(&message.origin_address);
+
+                let data = vec![9, 8, 7];
+                socket
+                    .send((data, message.interface))
+                    .await
+                    .expect("could not send data");
+            }
+        }
+    });
+}
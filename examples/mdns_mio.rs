@@ -0,0 +1,57 @@
+#[cfg(windows)]
+fn main() {
+    eprintln!("mio sources are only available on unix platforms");
+}
+
+#[cfg(not(windows))]
+fn main() {
+    use mio::{Events, Interest, Poll, Token};
+    use multicast_socket::MulticastSocket;
+    use std::io;
+    use std::net::SocketAddrV4;
+
+    const MDNS: Token = Token(0);
+
+    let mdns_multicast_address = SocketAddrV4::new([224, 0, 0, 251].into(), 5353);
+
+    let mut socket = MulticastSocket::all_interfaces(mdns_multicast_address)
+        .expect("could not create and bind socket");
+
+    let mut poll = Poll::new().expect("could not create poll");
+    poll.registry()
+        .register(&mut socket, MDNS, Interest::READABLE)
+        .expect("could not register socket");
+
+    let data = vec![1, 2];
+    socket
+        .broadcast(&data)
+        .expect("could not broadcast message to ips being listened");
+
+    let mut events = Events::with_capacity(16);
+    loop {
+        poll.poll(&mut events, None).expect("could not poll");
+
+        for event in events.iter() {
+            if event.token() != MDNS {
+                continue;
+            }
+
+            // Drain every pending packet, until the socket reports it would block
+            loop {
+                match socket.receive() {
+                    Ok(message) => {
+                        dbg!(&message.interface);
+                        dbg!(&message.origin_address);
+
+                        let data = vec![9, 8, 7];
+                        socket
+                            .send(&data, &message.interface)
+                            .expect("could not send data");
+                    }
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                    Err(e) => panic!("could not receive: {}", e),
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,35 @@
+#[cfg(windows)]
+fn main() {
+    eprintln!("Tokio sockets are only available on unix platforms");
+}
+
+#[cfg(not(windows))]
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    use multicast_socket::AsyncMulticastSocket;
+    use std::net::SocketAddrV4;
+
+    let mdns_multicast_address = SocketAddrV4::new([224, 0, 0, 251].into(), 5353);
+
+    let socket = AsyncMulticastSocket::all_interfaces(mdns_multicast_address)
+        .expect("could not create and bind socket");
+
+    let data = vec![1, 2];
+    socket
+        .broadcast(&data)
+        .await
+        .expect("could not broadcast message to ips being listened");
+
+    loop {
+        if let Ok(message) = socket.receive().await {
+            dbg!(&message.interface);
+            dbg!(&message.origin_address);
+
+            let data = vec![9, 8, 7];
+            socket
+                .send(&data, &message.interface)
+                .await
+                .expect("could not send data");
+        };
+    }
+}
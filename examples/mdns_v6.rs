@@ -0,0 +1,33 @@
+#[cfg(windows)]
+fn main() {
+    eprintln!("IPv6 sockets are only available on unix platforms");
+}
+
+#[cfg(not(windows))]
+fn main() {
+    use multicast_socket::MulticastSocketV6;
+    use std::net::SocketAddrV6;
+
+    let mdns_multicast_address =
+        SocketAddrV6::new([0xff02, 0, 0, 0, 0, 0, 0, 0xfb].into(), 5353, 0, 0);
+
+    let socket = MulticastSocketV6::all_interfaces(mdns_multicast_address)
+        .expect("could not create and bind socket");
+
+    let data = vec![1, 2];
+    socket
+        .broadcast(&data)
+        .expect("could not broadcast message to interfaces being listened");
+
+    loop {
+        if let Ok(message) = socket.receive() {
+            dbg!(&message.interface);
+            dbg!(&message.origin_address);
+
+            let data = vec![9, 8, 7];
+            socket
+                .send(&data, &message.interface)
+                .expect("could not send data");
+        };
+    }
+}
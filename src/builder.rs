@@ -0,0 +1,236 @@
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::time::Duration;
+
+use crate::error::{Context, Error, Operation};
+use crate::{create_on_interfaces, InterfaceInfo, MulticastOptions, MulticastSocket};
+
+type InterfacePredicate = Box<dyn Fn(&str, &Ipv4Addr) -> bool>;
+
+enum InterfaceSelector {
+    Address(Ipv4Addr),
+    Name(String),
+    Index(u32),
+    Filter(InterfacePredicate),
+}
+
+/// Configures and validates a [`MulticastSocket`] before creating it.
+///
+/// Created through [`MulticastSocket::builder`]. When no interface is selected, the group is
+/// joined on every IPv4 interface, like [`MulticastSocket::all_interfaces`] does.
+pub struct MulticastSocketBuilder {
+    multicast_address: SocketAddrV4,
+    options: MulticastOptions,
+    interfaces: Vec<InterfaceSelector>,
+}
+
+impl MulticastSocket {
+    pub fn builder(multicast_address: SocketAddrV4) -> MulticastSocketBuilder {
+        MulticastSocketBuilder {
+            multicast_address,
+            options: MulticastOptions::default(),
+            interfaces: Vec::new(),
+        }
+    }
+}
+
+impl MulticastSocketBuilder {
+    /// Replaces every option, keeping the interfaces selected so far.
+    pub fn options(mut self, options: MulticastOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Joins the group through the interface with this address.
+    pub fn interface(mut self, address: Ipv4Addr) -> Self {
+        self.interfaces.push(InterfaceSelector::Address(address));
+        self
+    }
+
+    /// Joins the group through the interface with this name, such as `eth0`.
+    pub fn interface_name(mut self, name: impl Into<String>) -> Self {
+        self.interfaces.push(InterfaceSelector::Name(name.into()));
+        self
+    }
+
+    /// Joins the group through the interface with this index.
+    pub fn interface_index(mut self, index: u32) -> Self {
+        self.interfaces.push(InterfaceSelector::Index(index));
+        self
+    }
+
+    /// Joins the group through every interface accepted by the predicate, which receives the
+    /// name and the IPv4 address of each interface, loopback included.
+    pub fn interface_predicate<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&str, &Ipv4Addr) -> bool + 'static,
+    {
+        self.interfaces
+            .push(InterfaceSelector::Filter(Box::new(predicate)));
+        self
+    }
+
+    /// See [`MulticastOptions::read_timeout`].
+    pub fn read_timeout(mut self, read_timeout: Option<Duration>) -> Self {
+        self.options.read_timeout = read_timeout;
+        self
+    }
+
+    pub fn loopback(mut self, loopback: bool) -> Self {
+        self.options.loopback = loopback;
+        self
+    }
+
+    /// See [`MulticastOptions::ttl`].
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.options.ttl = Some(ttl);
+        self
+    }
+
+    /// See [`MulticastOptions::tos`].
+    pub fn tos(mut self, tos: u32) -> Self {
+        self.options.tos = Some(tos);
+        self
+    }
+
+    /// See [`MulticastOptions::priority`].
+    pub fn priority(mut self, priority: u32) -> Self {
+        self.options.priority = Some(priority);
+        self
+    }
+
+    /// See [`MulticastOptions::min_ttl`].
+    pub fn min_ttl(mut self, min_ttl: u8) -> Self {
+        self.options.min_ttl = Some(min_ttl);
+        self
+    }
+
+    /// See [`MulticastOptions::timestamping`].
+    pub fn timestamping(mut self, timestamping: crate::Timestamping) -> Self {
+        self.options.timestamping = timestamping;
+        self
+    }
+
+    /// See [`MulticastOptions::reuse_address`].
+    pub fn reuse_address(mut self, reuse_address: bool) -> Self {
+        self.options.reuse_address = reuse_address;
+        self
+    }
+
+    /// See [`MulticastOptions::reuse_port`].
+    pub fn reuse_port(mut self, reuse_port: bool) -> Self {
+        self.options.reuse_port = reuse_port;
+        self
+    }
+
+    /// The size of the buffer allocated by [`MulticastSocket::receive`].
+    pub fn buffer_size(mut self, buffer_size: usize) -> Self {
+        self.options.buffer_size = buffer_size;
+        self
+    }
+
+    /// See [`MulticastOptions::receive_buffer_size`].
+    pub fn receive_buffer_size(mut self, size: usize) -> Self {
+        self.options.receive_buffer_size = Some(size);
+        self
+    }
+
+    /// See [`MulticastOptions::send_buffer_size`].
+    pub fn send_buffer_size(mut self, size: usize) -> Self {
+        self.options.send_buffer_size = Some(size);
+        self
+    }
+
+    /// See [`MulticastOptions::bind_address`].
+    pub fn bind_address(mut self, bind_address: Ipv4Addr) -> Self {
+        self.options.bind_address = bind_address;
+        self
+    }
+
+    /// See [`MulticastOptions::sources`].
+    pub fn source(mut self, source: Ipv4Addr) -> Self {
+        self.options.sources.push(source);
+        self
+    }
+
+    /// See [`MulticastOptions::broadcast_policy`].
+    pub fn broadcast_policy(mut self, broadcast_policy: crate::BroadcastPolicy) -> Self {
+        self.options.broadcast_policy = broadcast_policy;
+        self
+    }
+
+    /// See [`MulticastOptions::skip_failing_interfaces`].
+    pub fn skip_failing_interfaces(mut self, skip_failing_interfaces: bool) -> Self {
+        self.options.skip_failing_interfaces = skip_failing_interfaces;
+        self
+    }
+
+    /// Narrows down the selected interfaces, or every IPv4 interface when none was selected.
+    ///
+    /// See [`MulticastOptions::interface_filter`].
+    pub fn interface_filter(mut self, filter: crate::InterfaceFilter) -> Self {
+        self.options.interface_filter = Some(filter);
+        self
+    }
+
+    pub fn build(self) -> Result<MulticastSocket, Error> {
+        if !self.multicast_address.ip().is_multicast() {
+            return Err(Error::NotMulticast(*self.multicast_address.ip()));
+        }
+
+        let interfaces = if self.interfaces.is_empty() {
+            crate::all_ipv4_interfaces().context(Operation::DiscoverInterfaces)?
+        } else {
+            resolve_interfaces(&self.interfaces)?
+        };
+        if interfaces.is_empty() {
+            return Err(Error::NoInterfaces);
+        }
+
+        create_on_interfaces(self.options, interfaces, self.multicast_address)
+    }
+}
+
+fn resolve_interfaces(selectors: &[InterfaceSelector]) -> Result<Vec<Ipv4Addr>, Error> {
+    // Only the first address of each interface is joined, as joining the same interface twice
+    // fails
+    let discovered = crate::discover_interfaces().context(Operation::DiscoverInterfaces)?;
+
+    let mut interfaces = Vec::new();
+    for selector in selectors {
+        let selected: Vec<Ipv4Addr> = match selector {
+            InterfaceSelector::Address(address) => vec![*address],
+            InterfaceSelector::Name(wanted) => {
+                let found = discovered
+                    .iter()
+                    .find(|interface| interface.name == *wanted)
+                    .and_then(InterfaceInfo::ipv4);
+                match found {
+                    Some(address) => vec![address],
+                    None => return Err(Error::UnknownInterface(wanted.clone())),
+                }
+            }
+            InterfaceSelector::Index(wanted) => {
+                let found = discovered
+                    .iter()
+                    .find(|interface| interface.index == *wanted)
+                    .and_then(InterfaceInfo::ipv4);
+                match found {
+                    Some(address) => vec![address],
+                    None => return Err(Error::UnknownInterface(format!("#{}", wanted))),
+                }
+            }
+            InterfaceSelector::Filter(predicate) => discovered
+                .iter()
+                .filter_map(|interface| Some((interface, interface.ipv4()?)))
+                .filter(|(interface, address)| predicate(&interface.name, address))
+                .map(|(_, address)| address)
+                .collect(),
+        };
+        for address in selected {
+            if !interfaces.contains(&address) {
+                interfaces.push(address);
+            }
+        }
+    }
+    Ok(interfaces)
+}
@@ -0,0 +1,233 @@
+use std::error;
+use std::fmt;
+use std::io;
+use std::net::Ipv4Addr;
+
+use crate::Interface;
+
+/// The step of setting up or using a socket that failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Listing the interfaces of the host.
+    DiscoverInterfaces,
+    /// Creating the socket itself.
+    CreateSocket,
+    /// Setting a socket option, named after its constant, such as `IP_MULTICAST_LOOP`.
+    SetOption(&'static str),
+    /// Joining the group on an interface.
+    Join,
+    /// Leaving the group on an interface.
+    Leave,
+    /// Binding the socket to its port.
+    Bind,
+    /// Sending a packet through an interface.
+    Send,
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::DiscoverInterfaces => write!(f, "discover interfaces"),
+            Operation::CreateSocket => write!(f, "create socket"),
+            Operation::SetOption(option) => write!(f, "set {}", option),
+            Operation::Join => write!(f, "join group"),
+            Operation::Leave => write!(f, "leave group"),
+            Operation::Bind => write!(f, "bind socket"),
+            Operation::Send => write!(f, "send"),
+        }
+    }
+}
+
+impl fmt::Display for Interface {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Interface::Default => write!(f, "default"),
+            Interface::Ip(address) => write!(f, "{}", address),
+            Interface::Index(index) => write!(f, "#{}", index),
+            Interface::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// Describes what failed while creating or using a socket.
+///
+/// Methods returning [`io::Error`] carry this error as their inner error, keeping the
+/// [`io::ErrorKind`] of the failed call. As the errno moves to the inner error, it is found
+/// with [`io::Error::get_ref`] and [`downcast_ref`], then [`Error::raw_os_error`], along with
+/// the operation and the interface.
+///
+/// [`downcast_ref`]: https://doc.rust-lang.org/std/error/trait.Error.html#method.downcast_ref
+#[derive(Debug)]
+pub enum Error {
+    /// The group address is not in `224.0.0.0/4`.
+    NotMulticast(Ipv4Addr),
+    /// No interface was left to join the group on.
+    NoInterfaces,
+    /// An interface selected by name or index does not exist, or has no IPv4 address.
+    UnknownInterface(String),
+    /// A subnet could not be parsed, or has a prefix longer than 32 bits.
+    InvalidSubnet(String),
+    /// A system call failed.
+    Io {
+        operation: Operation,
+        /// The interface the operation was performed on, if it was specific to one.
+        interface: Option<Interface>,
+        source: io::Error,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotMulticast(address) => {
+                write!(f, "{} is not a multicast address", address)
+            }
+            Error::NoInterfaces => write!(f, "no interface to join the group on"),
+            Error::UnknownInterface(interface) => {
+                write!(f, "interface {} has no IPv4 address", interface)
+            }
+            Error::InvalidSubnet(subnet) => write!(f, "{} is not a valid subnet", subnet),
+            Error::Io {
+                operation,
+                interface: Some(interface),
+                source,
+            } => write!(
+                f,
+                "could not {} on interface {}: {}",
+                operation, interface, source
+            ),
+            Error::Io {
+                operation,
+                interface: None,
+                source,
+            } => write!(f, "could not {}: {}", operation, source),
+        }
+    }
+}
+
+impl Error {
+    /// The operation that failed, when a system call failed.
+    pub fn operation(&self) -> Option<Operation> {
+        match self {
+            Error::Io { operation, .. } => Some(*operation),
+            _ => None,
+        }
+    }
+
+    /// The errno of the failed system call, if any.
+    pub fn raw_os_error(&self) -> Option<i32> {
+        match self {
+            Error::Io { source, .. } => source.raw_os_error(),
+            _ => None,
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Wraps the error as the inner error, keeping the kind of the failed system call.
+impl From<Error> for io::Error {
+    fn from(e: Error) -> Self {
+        let kind = match &e {
+            Error::NotMulticast(_) | Error::InvalidSubnet(_) => io::ErrorKind::InvalidInput,
+            Error::NoInterfaces | Error::UnknownInterface(_) => io::ErrorKind::NotFound,
+            Error::Io { source, .. } => source.kind(),
+        };
+        io::Error::new(kind, e)
+    }
+}
+
+pub(crate) trait Context<T> {
+    /// Tags the error with the operation that failed.
+    fn context(self, operation: Operation) -> Result<T, Error>;
+
+    /// Tags the error with the operation that failed, and the interface it was performed on.
+    fn context_on(self, operation: Operation, interface: &Interface) -> Result<T, Error>;
+
+    /// Like [`Context::context_on`], for methods that return an [`io::Error`].
+    ///
+    /// [`io::ErrorKind::WouldBlock`] is returned untouched, as it is the expected outcome of
+    /// non-blocking sockets rather than a failure worth describing.
+    fn io_context_on(self, operation: Operation, interface: &Interface) -> io::Result<T>;
+}
+
+impl<T> Context<T> for io::Result<T> {
+    fn context(self, operation: Operation) -> Result<T, Error> {
+        self.map_err(|source| Error::Io {
+            operation,
+            interface: None,
+            source,
+        })
+    }
+
+    fn context_on(self, operation: Operation, interface: &Interface) -> Result<T, Error> {
+        self.map_err(|source| Error::Io {
+            operation,
+            interface: Some(interface.clone()),
+            source,
+        })
+    }
+
+    fn io_context_on(self, operation: Operation, interface: &Interface) -> io::Result<T> {
+        match self {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(e),
+            result => Ok(result.context_on(operation, interface)?),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `No such device`, as returned when joining on an interface that went away.
+    const ENODEV: i32 = 19;
+
+    #[test]
+    fn io_errors_carry_the_operation_interface_and_errno() {
+        let interface = Interface::Ip(Ipv4Addr::new(192, 0, 2, 1));
+        let result: io::Result<()> = Err(io::Error::from_raw_os_error(ENODEV));
+        let e = result
+            .io_context_on(Operation::Join, &interface)
+            .unwrap_err();
+
+        assert_eq!(e.kind(), io::Error::from_raw_os_error(ENODEV).kind());
+        let inner = e.get_ref().unwrap().downcast_ref::<Error>().unwrap();
+        assert_eq!(inner.operation(), Some(Operation::Join));
+        assert_eq!(inner.raw_os_error(), Some(ENODEV));
+        assert_eq!(
+            e.to_string(),
+            format!(
+                "could not join group on interface 192.0.2.1: {}",
+                io::Error::from_raw_os_error(ENODEV)
+            )
+        );
+    }
+
+    #[test]
+    fn would_block_is_left_untouched() {
+        let interface = Interface::Index(3);
+        let result: io::Result<()> = Err(io::ErrorKind::WouldBlock.into());
+        let e = result
+            .io_context_on(Operation::Send, &interface)
+            .unwrap_err();
+
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        assert!(e.get_ref().is_none());
+    }
+
+    #[test]
+    fn validation_errors_have_a_matching_kind() {
+        let e = io::Error::from(Error::NotMulticast(Ipv4Addr::new(10, 0, 0, 1)));
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+        let e = io::Error::from(Error::NoInterfaces);
+        assert_eq!(e.kind(), io::ErrorKind::NotFound);
+    }
+}
@@ -0,0 +1,389 @@
+use std::fmt;
+use std::io;
+use std::net::Ipv4Addr;
+use std::str::FromStr;
+
+use crate::error::{Context, Error, Operation};
+use crate::InterfaceInfo;
+
+/// Name patterns of the bridges, tunnels and virtual adapters created by container runtimes,
+/// hypervisors and VPNs.
+const VIRTUAL_ADAPTERS: &[&str] = &[
+    "docker*",
+    "br-*",
+    "veth*",
+    "virbr*",
+    "lxcbr*",
+    "lxdbr*",
+    "cni*",
+    "flannel*",
+    "cali*",
+    "vboxnet*",
+    "vmnet*",
+    "tun*",
+    "tap*",
+    "utun*",
+    "wg*",
+    "zt*",
+    "tailscale*",
+    "vEthernet*",
+];
+
+/// An IPv4 network, written as `192.168.1.0/24`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Ipv4Subnet {
+    address: Ipv4Addr,
+    prefix_len: u8,
+}
+
+impl Ipv4Subnet {
+    pub fn new(address: Ipv4Addr, prefix_len: u8) -> Result<Self, Error> {
+        if prefix_len > 32 {
+            return Err(Error::InvalidSubnet(format!("{}/{}", address, prefix_len)));
+        }
+        Ok(Ipv4Subnet {
+            address,
+            prefix_len,
+        })
+    }
+
+    pub fn contains(&self, address: &Ipv4Addr) -> bool {
+        let mask = u32::MAX
+            .checked_shl(32 - u32::from(self.prefix_len))
+            .unwrap_or(0);
+        u32::from(*address) & mask == u32::from(self.address) & mask
+    }
+}
+
+impl FromStr for Ipv4Subnet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Error> {
+        let invalid = || Error::InvalidSubnet(s.to_string());
+        let (address, prefix_len) = match s.split_once('/') {
+            Some((address, prefix_len)) => (address, prefix_len.parse().map_err(|_| invalid())?),
+            None => (s, 32),
+        };
+        let address = address.parse().map_err(|_| invalid())?;
+        Ipv4Subnet::new(address, prefix_len)
+    }
+}
+
+impl fmt::Display for Ipv4Subnet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.address, self.prefix_len)
+    }
+}
+
+/// Selects which interfaces a socket joins its group on.
+///
+/// Name patterns accept `*` for any run of characters and `?` for a single one. Interfaces
+/// must match at least one of the included patterns, when there are any, and their address
+/// must be within at least one of the included subnets, when there are any, so both kinds
+/// narrow the selection when both are set. Neither may match any of the excluded ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InterfaceFilter {
+    include_names: Vec<String>,
+    exclude_names: Vec<String>,
+    include_subnets: Vec<Ipv4Subnet>,
+    exclude_subnets: Vec<Ipv4Subnet>,
+    require_up: bool,
+    require_multicast: bool,
+    exclude_loopback: bool,
+}
+
+impl InterfaceFilter {
+    /// A filter accepting every interface.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn include_name(mut self, pattern: impl Into<String>) -> Self {
+        self.include_names.push(pattern.into());
+        self
+    }
+
+    pub fn exclude_name(mut self, pattern: impl Into<String>) -> Self {
+        self.exclude_names.push(pattern.into());
+        self
+    }
+
+    /// Only accepts addresses within the subnet.
+    pub fn include_subnet(mut self, subnet: Ipv4Subnet) -> Self {
+        self.include_subnets.push(subnet);
+        self
+    }
+
+    pub fn exclude_subnet(mut self, subnet: Ipv4Subnet) -> Self {
+        self.exclude_subnets.push(subnet);
+        self
+    }
+
+    /// Only accepts interfaces that are up and running.
+    pub fn require_up(mut self) -> Self {
+        self.require_up = true;
+        self
+    }
+
+    /// Only accepts interfaces flagged as supporting multicast.
+    pub fn require_multicast(mut self) -> Self {
+        self.require_multicast = true;
+        self
+    }
+
+    pub fn exclude_loopback(mut self) -> Self {
+        self.exclude_loopback = true;
+        self
+    }
+
+    /// Excludes the bridges and virtual adapters of Docker, libvirt, LXC, Kubernetes CNIs,
+    /// VirtualBox, VMware, Hyper-V, and of tunnels such as WireGuard, Tailscale and ZeroTier.
+    pub fn exclude_virtual(mut self) -> Self {
+        self.exclude_names
+            .extend(VIRTUAL_ADAPTERS.iter().map(|pattern| pattern.to_string()));
+        self
+    }
+
+    /// Whether the address of the interface is accepted.
+    pub fn matches(&self, interface: &InterfaceInfo, address: &Ipv4Addr) -> bool {
+        let name = interface.name.as_str();
+        (self.include_names.is_empty() || self.include_names.iter().any(|p| glob_match(p, name)))
+            && !self.exclude_names.iter().any(|p| glob_match(p, name))
+            && (self.include_subnets.is_empty()
+                || self.include_subnets.iter().any(|s| s.contains(address)))
+            && !self.exclude_subnets.iter().any(|s| s.contains(address))
+            && (!self.require_up || (interface.up && interface.running))
+            && (!self.require_multicast || interface.multicast)
+            && (!self.exclude_loopback || !interface.loopback)
+    }
+
+    /// Discovers the interfaces of the host, returning the first accepted address of each
+    /// accepted interface, ready to be passed to `MulticastSocket::with_options`.
+    pub fn ipv4_interfaces(&self) -> io::Result<Vec<Ipv4Addr>> {
+        Ok(crate::discover_interfaces()?
+            .iter()
+            .filter_map(|interface| {
+                interface
+                    .addresses
+                    .iter()
+                    .map(|address| address.address)
+                    .find(|address| self.matches(interface, address))
+            })
+            .collect())
+    }
+}
+
+/// Drops the interfaces rejected by
+/// [`MulticastOptions::interface_filter`](crate::MulticastOptions::interface_filter).
+pub(crate) fn filter_interfaces(
+    filter: Option<&InterfaceFilter>,
+    interfaces: Vec<Ipv4Addr>,
+) -> Result<Vec<Ipv4Addr>, Error> {
+    let filter = match filter {
+        Some(filter) => filter,
+        None => return Ok(interfaces),
+    };
+    if interfaces.is_empty() {
+        return Ok(interfaces);
+    }
+
+    let discovered = crate::discover_interfaces().context(Operation::DiscoverInterfaces)?;
+    let accepted: Vec<Ipv4Addr> = interfaces
+        .into_iter()
+        .filter(|address| {
+            discovered.iter().any(|interface| {
+                interface.has_address(address) && filter.matches(interface, address)
+            })
+        })
+        .collect();
+    if accepted.is_empty() {
+        return Err(Error::NoInterfaces);
+    }
+    Ok(accepted)
+}
+
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+
+    // Iterative matching, backtracking to the last `*` on a mismatch
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == name[n]) {
+            p += 1;
+            n += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            star = Some((p, n));
+            p += 1;
+        } else if let Some((star_p, star_n)) = star {
+            p = star_p + 1;
+            n = star_n + 1;
+            star = Some((star_p, star_n + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::InterfaceAddress;
+
+    fn interface(name: &str, address: Ipv4Addr) -> InterfaceInfo {
+        InterfaceInfo {
+            name: name.to_string(),
+            index: 2,
+            addresses: vec![InterfaceAddress {
+                address,
+                netmask: Ipv4Addr::new(255, 255, 255, 0),
+                broadcast: None,
+            }],
+            up: true,
+            running: true,
+            multicast: true,
+            loopback: false,
+            point_to_point: false,
+        }
+    }
+
+    #[test]
+    fn glob_matches_literals_and_wildcards() {
+        assert!(glob_match("eth0", "eth0"));
+        assert!(!glob_match("eth0", "eth1"));
+        assert!(glob_match("eth?", "eth1"));
+        assert!(!glob_match("eth?", "eth10"));
+        assert!(glob_match("docker*", "docker0"));
+        assert!(glob_match("docker*", "docker"));
+        assert!(glob_match("*", ""));
+        assert!(glob_match("*0", "veth0"));
+        assert!(!glob_match("*0", "veth1"));
+        assert!(!glob_match("", "eth0"));
+    }
+
+    #[test]
+    fn glob_backtracks_to_last_star() {
+        assert!(glob_match("a*b*c", "axxbyybc"));
+        assert!(glob_match("*ab", "aab"));
+        assert!(glob_match("a*?b", "axxb"));
+        assert!(!glob_match("a*b*c", "axxbyyb"));
+        assert!(!glob_match("a*?b", "ab"));
+    }
+
+    #[test]
+    fn subnet_contains() {
+        let subnet: Ipv4Subnet = "192.168.1.0/24".parse().unwrap();
+        assert!(subnet.contains(&Ipv4Addr::new(192, 168, 1, 77)));
+        assert!(!subnet.contains(&Ipv4Addr::new(192, 168, 2, 1)));
+
+        // Host bits of the subnet address are ignored
+        let subnet = Ipv4Subnet::new(Ipv4Addr::new(10, 1, 2, 3), 8).unwrap();
+        assert!(subnet.contains(&Ipv4Addr::new(10, 200, 0, 1)));
+    }
+
+    #[test]
+    fn subnet_edge_prefixes() {
+        let any: Ipv4Subnet = "0.0.0.0/0".parse().unwrap();
+        assert!(any.contains(&Ipv4Addr::new(203, 0, 113, 9)));
+        assert!(any.contains(&Ipv4Addr::BROADCAST));
+
+        let host: Ipv4Subnet = "10.0.0.1/32".parse().unwrap();
+        assert!(host.contains(&Ipv4Addr::new(10, 0, 0, 1)));
+        assert!(!host.contains(&Ipv4Addr::new(10, 0, 0, 2)));
+    }
+
+    #[test]
+    fn bare_address_is_a_host_subnet() {
+        let subnet: Ipv4Subnet = "10.0.0.1".parse().unwrap();
+        assert_eq!(
+            subnet,
+            Ipv4Subnet::new(Ipv4Addr::new(10, 0, 0, 1), 32).unwrap()
+        );
+        assert_eq!(subnet.to_string(), "10.0.0.1/32");
+    }
+
+    #[test]
+    fn rejects_invalid_subnets() {
+        assert!(matches!(
+            Ipv4Subnet::new(Ipv4Addr::UNSPECIFIED, 33),
+            Err(Error::InvalidSubnet(_))
+        ));
+        for invalid in [
+            "10.0.0.0/33",
+            "10.0.0/8",
+            "10.0.0.0/",
+            "10.0.0.0/x",
+            "10.0.0.0/-1",
+            "eth0",
+            "",
+        ] {
+            match invalid.parse::<Ipv4Subnet>() {
+                Err(Error::InvalidSubnet(subnet)) => assert_eq!(subnet, invalid),
+                other => panic!("{:?} parsed as {:?}", invalid, other),
+            }
+        }
+    }
+
+    #[test]
+    fn filter_includes_and_excludes() {
+        let eth0 = interface("eth0", Ipv4Addr::new(192, 168, 1, 10));
+        let docker0 = interface("docker0", Ipv4Addr::new(172, 17, 0, 1));
+
+        assert!(InterfaceFilter::new().matches(&eth0, &eth0.addresses[0].address));
+
+        let filter = InterfaceFilter::new().include_name("eth*");
+        assert!(filter.matches(&eth0, &eth0.addresses[0].address));
+        assert!(!filter.matches(&docker0, &docker0.addresses[0].address));
+
+        let filter = InterfaceFilter::new().exclude_virtual();
+        assert!(filter.matches(&eth0, &eth0.addresses[0].address));
+        assert!(!filter.matches(&docker0, &docker0.addresses[0].address));
+
+        let filter = InterfaceFilter::new()
+            .include_subnet("192.168.0.0/16".parse().unwrap())
+            .exclude_subnet("192.168.1.0/24".parse().unwrap());
+        assert!(filter.matches(&eth0, &Ipv4Addr::new(192, 168, 2, 10)));
+        assert!(!filter.matches(&eth0, &eth0.addresses[0].address));
+        assert!(!filter.matches(&docker0, &docker0.addresses[0].address));
+    }
+
+    #[test]
+    fn filter_requires_both_included_names_and_subnets() {
+        let filter = InterfaceFilter::new()
+            .include_name("eth*")
+            .include_subnet("10.0.0.0/8".parse().unwrap());
+        let eth0 = interface("eth0", Ipv4Addr::new(10, 0, 0, 2));
+        let wlan0 = interface("wlan0", Ipv4Addr::new(10, 0, 0, 3));
+
+        assert!(filter.matches(&eth0, &eth0.addresses[0].address));
+        assert!(!filter.matches(&eth0, &Ipv4Addr::new(192, 168, 1, 10)));
+        assert!(!filter.matches(&wlan0, &wlan0.addresses[0].address));
+    }
+
+    #[test]
+    fn filter_requires_flags() {
+        let address = Ipv4Addr::new(192, 168, 1, 10);
+        let mut down = interface("eth0", address);
+        down.running = false;
+        let mut no_multicast = interface("eth0", address);
+        no_multicast.multicast = false;
+        let mut loopback = interface("lo", Ipv4Addr::LOCALHOST);
+        loopback.loopback = true;
+
+        assert!(InterfaceFilter::new().matches(&down, &address));
+        assert!(!InterfaceFilter::new().require_up().matches(&down, &address));
+        assert!(!InterfaceFilter::new()
+            .require_multicast()
+            .matches(&no_multicast, &address));
+        assert!(!InterfaceFilter::new()
+            .exclude_loopback()
+            .matches(&loopback, &Ipv4Addr::LOCALHOST));
+        assert!(InterfaceFilter::new()
+            .require_up()
+            .require_multicast()
+            .exclude_loopback()
+            .matches(&interface("eth0", address), &address));
+    }
+}
@@ -0,0 +1,58 @@
+use std::io;
+use std::net::Ipv4Addr;
+
+/// A network interface of the host, as listed by [`discover_interfaces`](crate::discover_interfaces).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InterfaceInfo {
+    pub name: String,
+    /// The index used by [`Interface::Index`](crate::Interface::Index), and reported on
+    /// received messages.
+    pub index: u32,
+    pub addresses: Vec<InterfaceAddress>,
+    pub up: bool,
+    pub running: bool,
+    pub multicast: bool,
+    pub loopback: bool,
+    pub point_to_point: bool,
+}
+
+/// An IPv4 address assigned to an interface.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InterfaceAddress {
+    pub address: Ipv4Addr,
+    pub netmask: Ipv4Addr,
+    pub broadcast: Option<Ipv4Addr>,
+}
+
+impl InterfaceInfo {
+    /// The first IPv4 address of the interface, which is the one used to join groups.
+    pub fn ipv4(&self) -> Option<Ipv4Addr> {
+        self.addresses.first().map(|address| address.address)
+    }
+
+    /// Whether the address is assigned to this interface.
+    pub fn has_address(&self, address: &Ipv4Addr) -> bool {
+        self.addresses.iter().any(|a| a.address == *address)
+    }
+
+    /// Finds the interface with this index.
+    pub fn by_index(index: u32) -> io::Result<Option<InterfaceInfo>> {
+        Ok(crate::discover_interfaces()?
+            .into_iter()
+            .find(|interface| interface.index == index))
+    }
+
+    /// Finds the interface with this name, such as `eth0`.
+    pub fn by_name(name: &str) -> io::Result<Option<InterfaceInfo>> {
+        Ok(crate::discover_interfaces()?
+            .into_iter()
+            .find(|interface| interface.name == name))
+    }
+
+    /// Finds the interface this address is assigned to.
+    pub fn by_address(address: &Ipv4Addr) -> io::Result<Option<InterfaceInfo>> {
+        Ok(crate::discover_interfaces()?
+            .into_iter()
+            .find(|interface| interface.has_address(address)))
+    }
+}
@@ -1,6 +1,16 @@
-use std::net::Ipv4Addr;
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
 use std::time::Duration;
 
+mod builder;
+mod error;
+mod filter;
+mod interfaces;
+pub use builder::*;
+pub use error::*;
+pub use filter::*;
+pub use interfaces::*;
+
 #[cfg(windows)]
 mod win;
 #[cfg(windows)]
@@ -11,18 +21,88 @@ mod unix;
 #[cfg(not(windows))]
 pub use unix::*;
 
+#[derive(Debug, Clone)]
 pub struct MulticastOptions {
     /// The maximal timeout before [`MulticastSocket::receive`] returns.
     ///
     /// If this is `None`, [`MulticastSocket::receive`] will block until there is data to read.
     pub read_timeout: Option<Duration>,
     pub loopback: bool,
+    /// How many routers multicast packets may cross, from `IP_MULTICAST_TTL`.
+    ///
+    /// If this is `None`, the system default is used, which keeps packets on the local link.
+    /// [`MulticastScope::default_ttl`] suggests a value for the group. IPv6 sockets use it as
+    /// their hop limit.
+    pub ttl: Option<u32>,
+    /// The type of service byte of sent packets, from `IP_TOS`.
+    ///
+    /// The DSCP code point takes its upper six bits, so Expedited Forwarding (46) is set as
+    /// `46 << 2`. IPv6 sockets set it as their traffic class, through `IPV6_TCLASS`.
+    pub tos: Option<u32>,
+    /// The priority of sent packets in the queues of the host, from `SO_PRIORITY`.
+    ///
+    /// Only supported on Linux.
+    pub priority: Option<u32>,
+    /// Drops received packets that arrive with a lower TTL, which [`Message::ttl`] reports.
+    ///
+    /// Senders on the local link that set their TTL to 255 can be told apart this way, as
+    /// every router on the path lowers it (the Generalized TTL Security Mechanism, RFC 5082).
+    /// IPv6 sockets compare it to the hop limit instead. Only supported on Linux, and only
+    /// applied by `receive` and `receive_into`.
+    pub min_ttl: Option<u8>,
+    /// Which kernel timestamps are taken when packets arrive, reported as
+    /// [`Message::received_at`].
+    pub timestamping: Timestamping,
+    /// Allows other sockets to bind to the same address, through `SO_REUSEADDR`.
+    pub reuse_address: bool,
+    /// Allows other sockets to bind to the same port, through `SO_REUSEPORT`.
+    ///
+    /// Ignored on Windows, where `SO_REUSEADDR` already covers it.
+    pub reuse_port: bool,
+    /// How large a packet `receive` can read, not to be confused with
+    /// [`MulticastOptions::receive_buffer_size`].
     pub buffer_size: usize,
+    /// How many bytes of packets the kernel queues until they are received, from `SO_RCVBUF`.
+    ///
+    /// Bursts larger than this are dropped. The system caps the size, unless the process may
+    /// raise it through `SO_RCVBUFFORCE`, which Linux allows with `CAP_NET_ADMIN`. Use
+    /// `receive_buffer_size` on the socket to find out the size that was granted.
+    pub receive_buffer_size: Option<usize>,
+    /// How many bytes of packets the kernel queues until they are sent, from `SO_SNDBUF`.
+    ///
+    /// Capped like [`MulticastOptions::receive_buffer_size`], or raised through
+    /// `SO_SNDBUFFORCE`.
+    pub send_buffer_size: Option<usize>,
     /// The address to bind the socket to.
     ///
     /// Usually this will be Ipv4Addr::UNSPECIFIED, in order to listen for packets on all
     /// interfaces.
     pub bind_address: Ipv4Addr,
+    /// The address to bind IPv6 sockets to.
+    ///
+    /// Usually this will be Ipv6Addr::UNSPECIFIED, in order to listen for packets on all
+    /// interfaces. Only used by `MulticastSocketV6`, which is available on unix platforms.
+    pub bind_address_v6: Ipv6Addr,
+    /// Restricts the IPv4 membership to packets sent by these sources.
+    ///
+    /// When empty, the group is joined for any source. Otherwise, the socket joins the group
+    /// on every interface only for the listed senders (source-specific multicast), which is
+    /// required for groups in `232.0.0.0/8`.
+    ///
+    /// IPv6 sockets, including the IPv6 half of `DualStackMulticastSocket`, fail to be created
+    /// when sources are listed.
+    pub sources: Vec<Ipv4Addr>,
+    /// How `broadcast` handles interfaces that fail to send.
+    pub broadcast_policy: BroadcastPolicy,
+    /// Keeps creating the socket when joining the group fails on some of the interfaces.
+    ///
+    /// The failures are listed by `skipped_interfaces`. Creation still fails if no interface
+    /// could be joined.
+    pub skip_failing_interfaces: bool,
+    /// Narrows down the interfaces given to the IPv4 socket constructors.
+    ///
+    /// Creation fails if the filter rejects every interface.
+    pub interface_filter: Option<InterfaceFilter>,
 }
 
 impl Default for MulticastOptions {
@@ -30,8 +110,149 @@ impl Default for MulticastOptions {
         MulticastOptions {
             read_timeout: Some(Duration::from_secs(1)),
             loopback: true,
+            ttl: None,
+            tos: None,
+            priority: None,
+            min_ttl: None,
+            timestamping: Timestamping::default(),
+            reuse_address: true,
+            reuse_port: true,
             buffer_size: 512,
+            receive_buffer_size: None,
+            send_buffer_size: None,
             bind_address: Ipv4Addr::UNSPECIFIED,
+            bind_address_v6: Ipv6Addr::UNSPECIFIED,
+            sources: Vec::new(),
+            broadcast_policy: BroadcastPolicy::default(),
+            skip_failing_interfaces: false,
+            interface_filter: None,
+        }
+    }
+}
+
+/// Overrides of the socket options for a single packet.
+#[derive(Debug, Clone, Default)]
+pub struct SendOptions {
+    /// The TTL of this packet, instead of [`MulticastOptions::ttl`].
+    ///
+    /// Sent as an `IP_TTL` control message, which is only supported on Linux.
+    pub ttl: Option<u32>,
+    /// The type of service byte of this packet, instead of [`MulticastOptions::tos`].
+    ///
+    /// Sent as an `IP_TOS` control message, which is only supported on Linux.
+    pub tos: Option<u32>,
+}
+
+/// How far an IPv4 multicast group is meant to reach, from its address range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MulticastScope {
+    /// `224.0.0.0/24`, which routers never forward.
+    LinkLocal,
+    /// `239.0.0.0/8`, bounded by the routers of the site or organization.
+    AdminScoped,
+    /// Every other multicast address, including source-specific `232.0.0.0/8`.
+    Global,
+}
+
+impl MulticastScope {
+    pub fn of(group: &Ipv4Addr) -> Self {
+        match group.octets() {
+            [224, 0, 0, _] => MulticastScope::LinkLocal,
+            [239, ..] => MulticastScope::AdminScoped,
+            _ => MulticastScope::Global,
         }
     }
+
+    /// A TTL reaching the whole scope, following the customary thresholds of multicast
+    /// routers: 1 for the link, 32 for a site, and 64 beyond it.
+    pub fn default_ttl(&self) -> u32 {
+        match self {
+            MulticastScope::LinkLocal => 1,
+            MulticastScope::AdminScoped => 32,
+            MulticastScope::Global => 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BroadcastPolicy {
+    /// Stops at the first interface that fails to send, returning its error.
+    #[default]
+    FailFast,
+    /// Sends on every interface, only failing when none of them succeeded.
+    ///
+    /// Use `broadcast_report` to find out which interfaces failed.
+    BestEffort,
+}
+
+/// The source of the receive timestamps of [`MulticastOptions::timestamping`].
+///
+/// Timestamps are only supported on Linux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Timestamping {
+    #[default]
+    Off,
+    /// Taken by the kernel as packets arrive, through `SO_TIMESTAMPNS`.
+    Software,
+    /// Taken by the network card, through `SO_TIMESTAMPING`.
+    ///
+    /// The card must have receive timestamping turned on, such as with `hwstamp_ctl`. Packets
+    /// it did not stamp get a software timestamp instead.
+    Hardware,
+}
+
+/// The outcome of sending a broadcast through one interface.
+#[derive(Debug)]
+pub struct BroadcastResult {
+    pub interface: Interface,
+    /// The bytes sent, or why sending failed.
+    pub result: io::Result<usize>,
+}
+
+/// An interface left out when creating a socket with
+/// [`MulticastOptions::skip_failing_interfaces`].
+#[derive(Debug)]
+pub struct SkippedInterface {
+    pub interface: Interface,
+    /// Why joining the group failed.
+    pub error: Error,
+}
+
+/// Collapses the results of a [`BroadcastPolicy::BestEffort`] broadcast, returning the last
+/// error only if no interface succeeded.
+fn best_effort(report: Vec<BroadcastResult>) -> io::Result<()> {
+    let mut error = None;
+    for sent in report {
+        match sent.result {
+            Ok(_) => return Ok(()),
+            Err(e) => error = Some(e),
+        }
+    }
+    match error {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_of_group() {
+        let scope = |a, b, c, d| MulticastScope::of(&Ipv4Addr::new(a, b, c, d));
+        assert_eq!(scope(224, 0, 0, 251), MulticastScope::LinkLocal);
+        assert_eq!(scope(224, 0, 0, 0), MulticastScope::LinkLocal);
+        assert_eq!(scope(224, 0, 1, 129), MulticastScope::Global);
+        assert_eq!(scope(232, 1, 1, 1), MulticastScope::Global);
+        assert_eq!(scope(239, 0, 0, 0), MulticastScope::AdminScoped);
+        assert_eq!(scope(239, 255, 255, 250), MulticastScope::AdminScoped);
+    }
+
+    #[test]
+    fn default_ttl_of_scope() {
+        assert_eq!(MulticastScope::LinkLocal.default_ttl(), 1);
+        assert_eq!(MulticastScope::AdminScoped.default_ttl(), 32);
+        assert_eq!(MulticastScope::Global.default_ttl(), 64);
+    }
 }
@@ -1,50 +1,225 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
 use std::io;
 use std::mem;
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
-use std::os::unix::io::AsRawFd;
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, RawFd};
+use std::ptr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use socket2::{Domain, Protocol, Socket, Type};
 
+use nix::poll::{poll, PollFd, PollFlags};
 use nix::sys::socket as sock;
-use nix::sys::uio::IoVec;
 
-fn create_on_interfaces(
+use crate::error::{Context, Operation};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod batch;
+mod dual_stack;
+#[cfg(feature = "futures")]
+mod futures_socket;
+mod interfaces;
+mod ipv6;
+#[cfg(feature = "mio")]
+mod mio_source;
+#[cfg(feature = "tokio")]
+mod tokio_socket;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod watcher;
+pub use dual_stack::*;
+#[cfg(feature = "futures")]
+pub use futures_socket::*;
+pub use interfaces::*;
+pub use ipv6::*;
+#[cfg(feature = "tokio")]
+pub use tokio_socket::*;
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub use watcher::*;
+
+pub(crate) fn create_on_interfaces(
     options: crate::MulticastOptions,
     interfaces: Vec<Ipv4Addr>,
     multicast_address: SocketAddrV4,
-) -> io::Result<MulticastSocket> {
-    let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))?;
-    socket.set_read_timeout(options.read_timeout)?;
-    socket.set_multicast_loop_v4(options.loopback)?;
-    socket.set_reuse_address(true)?;
-    socket.set_reuse_port(true)?;
+) -> Result<MulticastSocket, crate::Error> {
+    let interfaces = crate::filter_interfaces(options.interface_filter.as_ref(), interfaces)?;
+
+    let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))
+        .context(Operation::CreateSocket)?;
+    socket
+        .set_read_timeout(options.read_timeout)
+        .context(Operation::SetOption("SO_RCVTIMEO"))?;
+    socket
+        .set_multicast_loop_v4(options.loopback)
+        .context(Operation::SetOption("IP_MULTICAST_LOOP"))?;
+    if let Some(ttl) = options.ttl {
+        socket
+            .set_multicast_ttl_v4(ttl)
+            .context(Operation::SetOption("IP_MULTICAST_TTL"))?;
+    }
+    if let Some(tos) = options.tos {
+        set_int_option(&socket, libc::IPPROTO_IP, libc::IP_TOS, tos as _)
+            .context(Operation::SetOption("IP_TOS"))?;
+    }
+    if let Some(priority) = options.priority {
+        set_priority(&socket, priority).context(Operation::SetOption("SO_PRIORITY"))?;
+    }
+    socket
+        .set_reuse_address(options.reuse_address)
+        .context(Operation::SetOption("SO_REUSEADDR"))?;
+    socket
+        .set_reuse_port(options.reuse_port)
+        .context(Operation::SetOption("SO_REUSEPORT"))?;
+    set_buffer_sizes(&socket, &options)?;
 
     // Ipv4PacketInfo translates to `IP_PKTINFO`. Checkout the [ip
     // manpage](https://man7.org/linux/man-pages/man7/ip.7.html) for more details. In summary
     // setting this option allows for determining on which interface a packet was received.
     sock::setsockopt(socket.as_raw_fd(), sock::sockopt::Ipv4PacketInfo, &true)
-        .map_err(nix_to_io_error)?;
+        .map_err(nix_to_io_error)
+        .context(Operation::SetOption("IP_PKTINFO"))?;
+    // Reports the type of service byte of received packets, as `IP_TOS` control messages
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    set_int_option(&socket, libc::IPPROTO_IP, libc::IP_RECVTOS, 1)
+        .context(Operation::SetOption("IP_RECVTOS"))?;
+    // Reports the TTL of received packets, as `IP_TTL` control messages
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    set_int_option(&socket, libc::IPPROTO_IP, libc::IP_RECVTTL, 1)
+        .context(Operation::SetOption("IP_RECVTTL"))?;
+    set_timestamping(&socket, options.timestamping)?;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    if options.min_ttl.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "received TTLs are only reported on Linux",
+        ))
+        .context(Operation::SetOption("IP_RECVTTL"));
+    }
 
-    for interface in &interfaces {
-        socket.join_multicast_v4(multicast_address.ip(), &interface)?;
+    let mut joined = Vec::with_capacity(interfaces.len());
+    let mut skipped = Vec::new();
+    for interface in interfaces {
+        let result = join_interface(
+            &socket,
+            multicast_address.ip(),
+            &options.sources,
+            &interface,
+        )
+        .context_on(Operation::Join, &Interface::Ip(interface));
+        match result {
+            Ok(()) => joined.push(interface),
+            Err(error) if options.skip_failing_interfaces => {
+                skipped.push(crate::SkippedInterface {
+                    interface: Interface::Ip(interface),
+                    error,
+                })
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    if joined.is_empty() && !skipped.is_empty() {
+        return Err(skipped.remove(0).error);
     }
 
-    socket.bind(&SocketAddr::new(options.bind_address.into(), multicast_address.port()).into())?;
+    socket
+        .bind(&SocketAddr::new(options.bind_address.into(), multicast_address.port()).into())
+        .context(Operation::Bind)?;
+
+    let memberships = joined
+        .iter()
+        .flat_map(|interface| memberships_on(multicast_address.ip(), &options.sources, interface))
+        .collect();
 
     Ok(MulticastSocket {
         socket,
-        interfaces,
+        memberships: Mutex::new(memberships),
         multicast_address,
         buffer_size: options.buffer_size,
+        broadcast_policy: options.broadcast_policy,
+        min_ttl: options.min_ttl,
+        skipped,
+        sources: options.sources,
+        interface_filter: options.interface_filter,
     })
 }
 
+/// Joins the group on the interface, for any source or only for the listed ones.
+fn join_interface(
+    socket: &Socket,
+    group: &Ipv4Addr,
+    sources: &[Ipv4Addr],
+    interface: &Ipv4Addr,
+) -> io::Result<()> {
+    if sources.is_empty() {
+        return socket.join_multicast_v4(group, interface);
+    }
+
+    for (joined, source) in sources.iter().enumerate() {
+        let result = set_source_membership(
+            socket,
+            libc::IP_ADD_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
+        );
+        if let Err(e) = result {
+            // Leave the sources already joined, so a skipped interface is not half joined
+            for source in &sources[..joined] {
+                let _ = set_source_membership(
+                    socket,
+                    libc::IP_DROP_SOURCE_MEMBERSHIP,
+                    group,
+                    source,
+                    interface,
+                );
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// The memberships added by [`join_interface`], one per source.
+fn memberships_on(group: &Ipv4Addr, sources: &[Ipv4Addr], interface: &Ipv4Addr) -> Vec<Membership> {
+    if sources.is_empty() {
+        return vec![Membership {
+            group: *group,
+            interface: *interface,
+            source: None,
+        }];
+    }
+    sources
+        .iter()
+        .map(|source| Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        })
+        .collect()
+}
+
 pub struct MulticastSocket {
     socket: socket2::Socket,
-    interfaces: Vec<Ipv4Addr>,
+    memberships: Mutex<Vec<Membership>>,
     multicast_address: SocketAddrV4,
     buffer_size: usize,
+    broadcast_policy: crate::BroadcastPolicy,
+    min_ttl: Option<u8>,
+    skipped: Vec<crate::SkippedInterface>,
+    // Kept so the group of the socket is always joined with the same sources, and addresses
+    // assigned later with the same filter
+    sources: Vec<Ipv4Addr>,
+    #[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+    interface_filter: Option<crate::InterfaceFilter>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Membership {
+    group: Ipv4Addr,
+    interface: Ipv4Addr,
+    /// The only sender accepted, for source-specific joins.
+    source: Option<Ipv4Addr>,
 }
 
 #[derive(Debug, Clone)]
@@ -52,6 +227,44 @@ pub enum Interface {
     Default,
     Ip(Ipv4Addr),
     Index(i32),
+    /// An interface name, such as `eth0`, resolved to its index on every send.
+    Name(String),
+}
+
+impl Interface {
+    /// Resolves the name of the interface, through `if_indextoname` for indexes.
+    pub fn name(&self) -> io::Result<String> {
+        match self {
+            Interface::Name(name) => Ok(name.clone()),
+            Interface::Index(index) => index_to_name(*index as _),
+            Interface::Ip(address) => crate::InterfaceInfo::by_address(address)?
+                .map(|interface| interface.name)
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::NotFound,
+                        format!("no interface has the address {}", address),
+                    )
+                }),
+            Interface::Default => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the default interface has no name",
+            )),
+        }
+    }
+}
+
+fn name_to_index(name: &str) -> io::Result<u32> {
+    nix::net::if_::if_nametoindex(name).map_err(nix_to_io_error)
+}
+
+fn index_to_name(index: u32) -> io::Result<String> {
+    let mut name = [0 as libc::c_char; libc::IF_NAMESIZE];
+    let r = unsafe { libc::if_indextoname(index, name.as_mut_ptr()) };
+    if r.is_null() {
+        return Err(io::Error::last_os_error());
+    }
+    let name = unsafe { std::ffi::CStr::from_ptr(name.as_ptr()) };
+    Ok(name.to_string_lossy().into_owned())
 }
 
 #[derive(Debug, Clone)]
@@ -59,6 +272,77 @@ pub struct Message {
     pub data: Vec<u8>,
     pub origin_address: SocketAddrV4,
     pub interface: Interface,
+    /// The address the packet was sent to, identifying which of the joined groups it belongs
+    /// to, or the address of this host for unicast packets.
+    pub destination_address: Ipv4Addr,
+    /// The local address of the interface the packet was received on.
+    ///
+    /// Replying with [`Interface::Ip`] of this address makes it the source of the reply.
+    pub interface_address: Ipv4Addr,
+    /// Whether the packet was larger than the receive buffer, and its data was cut short.
+    ///
+    /// Increase [`MulticastOptions::buffer_size`](crate::MulticastOptions::buffer_size) when
+    /// this happens.
+    pub truncated: bool,
+    /// The type of service byte of the packet, only reported on Linux.
+    pub tos: Option<u8>,
+    /// The TTL the packet arrived with, only reported on Linux.
+    pub ttl: Option<u8>,
+    /// When the packet arrived, if
+    /// [`MulticastOptions::timestamping`](crate::MulticastOptions::timestamping) is on.
+    pub received_at: Option<SystemTime>,
+}
+
+impl Message {
+    /// Whether the packet was sent to a multicast group, instead of directly to this host.
+    pub fn is_multicast(&self) -> bool {
+        self.destination_address.is_multicast()
+    }
+
+    /// Resolves the name of the interface the packet was received on, such as `wlan0`.
+    pub fn interface_name(&self) -> io::Result<String> {
+        self.interface.name()
+    }
+
+    /// The DSCP code point, from the upper six bits of [`Message::tos`].
+    pub fn dscp(&self) -> Option<u8> {
+        self.tos.map(|tos| tos >> 2)
+    }
+
+    fn from_meta(data: Vec<u8>, meta: MessageMeta) -> Self {
+        Message {
+            data,
+            origin_address: meta.origin_address,
+            interface: meta.interface,
+            destination_address: meta.destination_address,
+            interface_address: meta.interface_address,
+            truncated: meta.truncated,
+            tos: meta.tos,
+            ttl: meta.ttl,
+            received_at: meta.received_at,
+        }
+    }
+}
+
+/// The details of a packet received with [`MulticastSocket::receive_into`].
+#[derive(Debug, Clone)]
+pub struct MessageMeta {
+    /// How many bytes of the buffer were filled with the packet data.
+    pub len: usize,
+    pub origin_address: SocketAddrV4,
+    pub interface: Interface,
+    /// See [`Message::destination_address`].
+    pub destination_address: Ipv4Addr,
+    /// See [`Message::interface_address`].
+    pub interface_address: Ipv4Addr,
+    /// See [`Message::truncated`].
+    pub truncated: bool,
+    /// See [`Message::tos`].
+    pub tos: Option<u8>,
+    /// See [`Message::ttl`].
+    pub ttl: Option<u8>,
+    /// See [`Message::received_at`].
+    pub received_at: Option<SystemTime>,
 }
 
 pub fn all_ipv4_interfaces() -> io::Result<Vec<Ipv4Addr>> {
@@ -68,22 +352,24 @@ pub fn all_ipv4_interfaces() -> io::Result<Vec<Ipv4Addr>> {
     // https://stackoverflow.com/questions/49819010/ip-add-membership-fails-when-set-both-on-interface-and-its-subinterface-is-that
     let mut collected_interfaces = HashMap::with_capacity(interfaces.len());
     for interface in interfaces {
-        if !collected_interfaces.contains_key(&interface.name) {
-            match interface.ip() {
-                std::net::IpAddr::V4(v4) if !interface.is_loopback() => {
-                    collected_interfaces.insert(interface.name, v4);
-                }
-                _ => {}
+        match interface.ip() {
+            std::net::IpAddr::V4(v4) if !interface.is_loopback() => {
+                collected_interfaces.entry(interface.name).or_insert(v4);
             }
+            _ => {}
         }
     }
-    Ok(collected_interfaces.into_iter().map(|(_, ip)| ip).collect())
+    Ok(collected_interfaces.into_values().collect())
 }
 
 impl MulticastSocket {
     pub fn all_interfaces(multicast_address: SocketAddrV4) -> io::Result<Self> {
-        let interfaces = all_ipv4_interfaces()?;
-        create_on_interfaces(Default::default(), interfaces, multicast_address)
+        let interfaces = all_ipv4_interfaces().context(Operation::DiscoverInterfaces)?;
+        Ok(create_on_interfaces(
+            Default::default(),
+            interfaces,
+            multicast_address,
+        )?)
     }
 
     pub fn with_options(
@@ -91,76 +377,732 @@ impl MulticastSocket {
         interfaces: Vec<Ipv4Addr>,
         options: crate::MulticastOptions,
     ) -> io::Result<Self> {
-        create_on_interfaces(options, interfaces, multicast_address)
+        Ok(create_on_interfaces(
+            options,
+            interfaces,
+            multicast_address,
+        )?)
+    }
+}
+
+fn set_source_membership(
+    socket: &Socket,
+    option: libc::c_int,
+    group: &Ipv4Addr,
+    source: &Ipv4Addr,
+    interface: &Ipv4Addr,
+) -> io::Result<()> {
+    // The field order of ip_mreq_source differs between Linux and BSDs, so it must be built by
+    // name instead of through a positional struct
+    let request = libc::ip_mreq_source {
+        imr_multiaddr: to_in_addr(group),
+        imr_sourceaddr: to_in_addr(source),
+        imr_interface: to_in_addr(interface),
+    };
+
+    let r = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            option,
+            &request as *const _ as *const libc::c_void,
+            mem::size_of_val(&request) as libc::socklen_t,
+        )
+    };
+    if r == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn set_int_option(
+    socket: &Socket,
+    level: libc::c_int,
+    option: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    let r = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            option,
+            &value as *const _ as *const libc::c_void,
+            mem::size_of_val(&value) as libc::socklen_t,
+        )
+    };
+    if r == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_priority(socket: &Socket, priority: u32) -> io::Result<()> {
+    set_int_option(socket, libc::SOL_SOCKET, libc::SO_PRIORITY, priority as _)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_priority(_socket: &Socket, _priority: u32) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "SO_PRIORITY is only supported on Linux",
+    ))
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_timestamping(
+    socket: &Socket,
+    timestamping: crate::Timestamping,
+) -> Result<(), crate::Error> {
+    match timestamping {
+        crate::Timestamping::Off => Ok(()),
+        crate::Timestamping::Software => {
+            set_int_option(socket, libc::SOL_SOCKET, libc::SO_TIMESTAMPNS, 1)
+                .context(Operation::SetOption("SO_TIMESTAMPNS"))
+        }
+        crate::Timestamping::Hardware => {
+            let flags = libc::SOF_TIMESTAMPING_RX_HARDWARE
+                | libc::SOF_TIMESTAMPING_RAW_HARDWARE
+                | libc::SOF_TIMESTAMPING_RX_SOFTWARE
+                | libc::SOF_TIMESTAMPING_SOFTWARE;
+            set_int_option(socket, libc::SOL_SOCKET, libc::SO_TIMESTAMPING, flags as _)
+                .context(Operation::SetOption("SO_TIMESTAMPING"))
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn set_timestamping(
+    _socket: &Socket,
+    timestamping: crate::Timestamping,
+) -> Result<(), crate::Error> {
+    match timestamping {
+        crate::Timestamping::Off => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "receive timestamps are only supported on Linux",
+        ))
+        .context(Operation::SetOption("SO_TIMESTAMPING")),
+    }
+}
+
+fn set_buffer_sizes(
+    socket: &Socket,
+    options: &crate::MulticastOptions,
+) -> Result<(), crate::Error> {
+    if let Some(size) = options.receive_buffer_size {
+        set_buffer_size(socket, libc::SO_RCVBUF, FORCE_RCVBUF, size)
+            .context(Operation::SetOption("SO_RCVBUF"))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        set_buffer_size(socket, libc::SO_SNDBUF, FORCE_SNDBUF, size)
+            .context(Operation::SetOption("SO_SNDBUF"))?;
+    }
+    Ok(())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const FORCE_RCVBUF: Option<libc::c_int> = Some(libc::SO_RCVBUFFORCE);
+#[cfg(any(target_os = "linux", target_os = "android"))]
+const FORCE_SNDBUF: Option<libc::c_int> = Some(libc::SO_SNDBUFFORCE);
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const FORCE_RCVBUF: Option<libc::c_int> = None;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+const FORCE_SNDBUF: Option<libc::c_int> = None;
+
+/// Sets the buffer size past the system cap when privileged, and up to the cap otherwise.
+fn set_buffer_size(
+    socket: &Socket,
+    option: libc::c_int,
+    force: Option<libc::c_int>,
+    size: usize,
+) -> io::Result<()> {
+    let size = libc::c_int::try_from(size).unwrap_or(libc::c_int::MAX);
+    if let Some(force) = force {
+        match set_int_option(socket, libc::SOL_SOCKET, force, size) {
+            Err(e) if e.kind() == io::ErrorKind::PermissionDenied => {}
+            result => return result,
+        }
+    }
+    set_int_option(socket, libc::SOL_SOCKET, option, size)
+}
+
+fn to_in_addr(addr: &Ipv4Addr) -> libc::in_addr {
+    libc::in_addr {
+        s_addr: u32::from_ne_bytes(addr.octets()),
+    }
+}
+
+impl AsRawFd for MulticastSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+impl AsFd for MulticastSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // The descriptor is owned by the socket, and stays open for as long as it is borrowed
+        unsafe { BorrowedFd::borrow_raw(self.socket.as_raw_fd()) }
     }
 }
 
 fn nix_to_io_error(e: nix::Error) -> io::Error {
-    io::Error::new(io::ErrorKind::Other, e)
+    match e {
+        // Keep the errno, so the error kind can be matched on, such as WouldBlock
+        nix::Error::Sys(errno) => io::Error::from_raw_os_error(errno as i32),
+        e => io::Error::new(io::ErrorKind::Other, e),
+    }
+}
+
+/// Room for the control messages enabled on the socket, kept on the stack to avoid allocating
+/// on every receive.
+const CONTROL_BUFFER_SIZE: usize = 256;
+
+// Control messages are read as `cmsghdr`, which must be aligned as such
+#[repr(C, align(8))]
+struct ControlBuffer([u8; CONTROL_BUFFER_SIZE]);
+
+impl ControlBuffer {
+    fn new() -> Self {
+        ControlBuffer([0; CONTROL_BUFFER_SIZE])
+    }
+}
+
+/// Prepares a `msghdr` receiving into the data buffer, with the origin written to a
+/// `sockaddr_in` or `sockaddr_in6`.
+fn receive_header<A>(
+    origin: &mut A,
+    data: &mut libc::iovec,
+    control: &mut ControlBuffer,
+) -> libc::msghdr {
+    let mut header: libc::msghdr = unsafe { mem::zeroed() };
+    header.msg_name = origin as *mut _ as *mut _;
+    header.msg_namelen = mem::size_of_val(origin) as _;
+    header.msg_iov = data;
+    header.msg_iovlen = 1;
+    header.msg_control = control.0.as_mut_ptr() as *mut _;
+    header.msg_controllen = CONTROL_BUFFER_SIZE as _;
+    header
+}
+
+/// Prepares a `msghdr` sending the data buffer to the destination, through the interface,
+/// with the per-packet overrides as control messages.
+fn send_header(
+    destination: &socket2::SockAddr,
+    data: &mut libc::iovec,
+    control: &mut ControlBuffer,
+    interface: &Interface,
+    options: &crate::SendOptions,
+) -> io::Result<libc::msghdr> {
+    let mut header: libc::msghdr = unsafe { mem::zeroed() };
+    header.msg_name = destination.as_ptr() as *mut _;
+    header.msg_namelen = destination.len();
+    header.msg_iov = data;
+    header.msg_iovlen = 1;
+
+    let pkt_info = packet_info(interface)?;
+    let ttl = options.ttl.map(|ttl| ttl as libc::c_int);
+    let tos = options.tos.map(|tos| tos as libc::c_int);
+
+    let mut length = control_space::<libc::in_pktinfo>();
+    for _ in [ttl, tos].iter().flatten() {
+        per_packet_options_supported()?;
+        length += control_space::<libc::c_int>();
+    }
+    header.msg_control = control.0.as_mut_ptr() as *mut _;
+    header.msg_controllen = length as _;
+
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&header);
+        let mut cmsg = write_control(&header, cmsg, libc::IP_PKTINFO, pkt_info);
+        if let Some(ttl) = ttl {
+            cmsg = write_control(&header, cmsg, libc::IP_TTL, ttl);
+        }
+        if let Some(tos) = tos {
+            write_control(&header, cmsg, libc::IP_TOS, tos);
+        }
+    }
+    Ok(header)
+}
+
+fn control_space<T>() -> usize {
+    unsafe { libc::CMSG_SPACE(mem::size_of::<T>() as _) as usize }
+}
+
+/// Writes an `IPPROTO_IP` control message, returning where the next one goes.
+unsafe fn write_control<T>(
+    header: &libc::msghdr,
+    cmsg: *mut libc::cmsghdr,
+    kind: libc::c_int,
+    value: T,
+) -> *mut libc::cmsghdr {
+    (*cmsg).cmsg_level = libc::IPPROTO_IP;
+    (*cmsg).cmsg_type = kind;
+    (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<T>() as _) as _;
+    ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut T, value);
+    libc::CMSG_NXTHDR(header, cmsg)
+}
+
+/// Only Linux accepts `IP_TTL` and `IP_TOS` as control messages on `sendmsg`, so other
+/// platforms report them as unsupported instead of silently ignoring them.
+fn per_packet_options_supported() -> io::Result<()> {
+    if cfg!(any(target_os = "linux", target_os = "android")) {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "per-packet options are only supported on Linux",
+        ))
+    }
+}
+
+/// Builds the `IP_PKTINFO` selecting the interface packets are sent through.
+fn packet_info(interface: &Interface) -> io::Result<libc::in_pktinfo> {
+    let mut pkt_info: libc::in_pktinfo = unsafe { mem::zeroed() };
+
+    match interface {
+        Interface::Default => {}
+        Interface::Ip(address) => pkt_info.ipi_spec_dst = to_in_addr(address),
+        Interface::Index(index) => pkt_info.ipi_ifindex = *index as _,
+        Interface::Name(name) => pkt_info.ipi_ifindex = name_to_index(name)? as _,
+    };
+
+    Ok(pkt_info)
+}
+
+fn message_meta(header: &libc::msghdr, origin: &libc::sockaddr_in, len: usize) -> MessageMeta {
+    let origin_address = if origin.sin_family as libc::c_int == libc::AF_INET {
+        SocketAddrV4::new(
+            Ipv4Addr::from(origin.sin_addr.s_addr.to_ne_bytes()),
+            u16::from_be(origin.sin_port),
+        )
+    } else {
+        SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0)
+    };
+
+    let mut meta = MessageMeta {
+        len,
+        origin_address,
+        interface: Interface::Default,
+        destination_address: Ipv4Addr::UNSPECIFIED,
+        interface_address: Ipv4Addr::UNSPECIFIED,
+        truncated: header.msg_flags & libc::MSG_TRUNC != 0,
+        tos: None,
+        ttl: None,
+        received_at: None,
+    };
+    parse_control_messages(header, &mut meta);
+    meta
+}
+
+fn parse_control_messages(header: &libc::msghdr, meta: &mut MessageMeta) {
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(header) };
+    while !cmsg.is_null() {
+        let (level, kind) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        match (level, kind) {
+            (libc::IPPROTO_IP, libc::IP_PKTINFO) => {
+                let pktinfo: libc::in_pktinfo =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                meta.interface = Interface::Index(pktinfo.ipi_ifindex as _);
+                meta.destination_address = Ipv4Addr::from(pktinfo.ipi_addr.s_addr.to_ne_bytes());
+                meta.interface_address = Ipv4Addr::from(pktinfo.ipi_spec_dst.s_addr.to_ne_bytes());
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::IPPROTO_IP, libc::IP_TOS) => {
+                meta.tos = Some(unsafe { *libc::CMSG_DATA(cmsg) });
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::IPPROTO_IP, libc::IP_TTL) => {
+                let ttl: libc::c_int =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                meta.ttl = Some(ttl as u8);
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::SOL_SOCKET, _) => meta.received_at = read_timestamp(cmsg).or(meta.received_at),
+            _ => {}
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(header, cmsg) };
+    }
+}
+
+/// Rounds the timeout up to whole milliseconds for `poll`, so sub-millisecond timeouts do not
+/// turn into a busy loop.
+fn poll_timeout(timeout: Duration) -> libc::c_int {
+    ((timeout.as_nanos() + 999_999) / 1_000_000).min(libc::c_int::MAX as _) as _
+}
+
+fn read_timeout_expired() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::WouldBlock,
+        "no packet received before the read timeout",
+    )
+}
+
+/// Waits for another packet after one was dropped for its TTL, within what is left of the read
+/// timeout that started with the first `recvmsg`, which would otherwise start over.
+fn wait_for_next(socket: &Socket, started: Instant) -> io::Result<()> {
+    let flags = unsafe { libc::fcntl(socket.as_raw_fd(), libc::F_GETFL) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Non-blocking sockets report that no packet is left by themselves
+    if flags & libc::O_NONBLOCK != 0 {
+        return Ok(());
+    }
+    let timeout = match socket.read_timeout()? {
+        Some(timeout) => timeout,
+        None => return Ok(()),
+    };
+
+    let remaining = (started + timeout).saturating_duration_since(Instant::now());
+    if remaining == Duration::from_secs(0) {
+        return Err(read_timeout_expired());
+    }
+    let mut fds = [PollFd::new(socket.as_raw_fd(), PollFlags::POLLIN)];
+    if poll(&mut fds, poll_timeout(remaining)).map_err(nix_to_io_error)? == 0 {
+        return Err(read_timeout_expired());
+    }
+    Ok(())
+}
+
+/// Reads the timestamp of a `SOL_SOCKET` control message, enabled by
+/// [`MulticastOptions::timestamping`](crate::MulticastOptions::timestamping).
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn read_timestamp(cmsg: *const libc::cmsghdr) -> Option<SystemTime> {
+    match unsafe { (*cmsg).cmsg_type } {
+        libc::SCM_TIMESTAMPNS => {
+            let stamp: libc::timespec =
+                unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+            to_system_time(&stamp)
+        }
+        // Holds the software, deprecated, and raw hardware timestamps, in that order
+        libc::SCM_TIMESTAMPING => {
+            let stamps: [libc::timespec; 3] =
+                unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+            to_system_time(&stamps[2]).or_else(|| to_system_time(&stamps[0]))
+        }
+        _ => None,
+    }
+}
+
+/// Converts a kernel timestamp, which is zero when the packet was not stamped.
+#[cfg_attr(not(any(target_os = "linux", target_os = "android")), allow(dead_code))]
+fn to_system_time(stamp: &libc::timespec) -> Option<SystemTime> {
+    if stamp.tv_sec == 0 && stamp.tv_nsec == 0 {
+        return None;
+    }
+    Some(UNIX_EPOCH + Duration::new(stamp.tv_sec as u64, stamp.tv_nsec as u32))
 }
 
 impl MulticastSocket {
     pub fn receive(&self) -> io::Result<Message> {
-        let mut data_buffer = vec![0; self.buffer_size];
-        let mut control_buffer = nix::cmsg_space!(libc::in_pktinfo);
-
-        let message = sock::recvmsg(
-            self.socket.as_raw_fd(),
-            &[IoVec::from_mut_slice(&mut data_buffer)],
-            Some(&mut control_buffer),
-            sock::MsgFlags::empty(),
-        )
-        .map_err(nix_to_io_error)?;
+        let mut data = vec![0; self.buffer_size];
+        let meta = self.receive_into(&mut data)?;
+        data.truncate(meta.len);
+        Ok(Message::from_meta(data, meta))
+    }
 
-        let origin_address = match message.address {
-            Some(sock::SockAddr::Inet(v4)) => Some(v4.to_std()),
-            _ => None,
+    /// Receives a packet into the buffer, without allocating.
+    ///
+    /// The packet data is written to `buf[..meta.len]`. Packets below
+    /// [`MulticastOptions::min_ttl`](crate::MulticastOptions::min_ttl) are dropped, and the
+    /// next one is waited for instead, within the same read timeout.
+    pub fn receive_into(&self, buf: &mut [u8]) -> io::Result<MessageMeta> {
+        let min_ttl = match self.min_ttl {
+            Some(min_ttl) => min_ttl,
+            None => return self.receive_any(buf, 0),
         };
-        let origin_address = match origin_address {
-            Some(SocketAddr::V4(v4)) => v4,
-            _ => SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 0),
+
+        let started = Instant::now();
+        loop {
+            let meta = self.receive_any(buf, 0)?;
+            match meta.ttl {
+                Some(ttl) if ttl < min_ttl => wait_for_next(&self.socket, started)?,
+                _ => return Ok(meta),
+            }
+        }
+    }
+
+    /// Receives a packet regardless of its TTL, with the `recvmsg` flags, such as
+    /// `MSG_DONTWAIT`.
+    fn receive_any(&self, buf: &mut [u8], flags: libc::c_int) -> io::Result<MessageMeta> {
+        let mut origin: libc::sockaddr_in = unsafe { mem::zeroed() };
+        let mut control = ControlBuffer::new();
+        let mut data = libc::iovec {
+            iov_base: buf.as_mut_ptr() as *mut _,
+            iov_len: buf.len(),
         };
+        let mut header = receive_header(&mut origin, &mut data, &mut control);
+
+        let read = unsafe { libc::recvmsg(self.socket.as_raw_fd(), &mut header, flags) };
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
 
-        let mut interface = Interface::Default;
+        Ok(message_meta(&header, &origin, read as usize))
+    }
+
+    /// Sends the buffer to the multicast group the socket was created with.
+    pub fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        self.send_to(buf, interface, &self.multicast_address)
+    }
 
-        for cmsg in message.cmsgs() {
-            if let sock::ControlMessageOwned::Ipv4PacketInfo(pktinfo) = cmsg {
-                interface = Interface::Index(pktinfo.ipi_ifindex as _);
+    /// Sends the buffer to another destination, such as any of the groups joined with
+    /// [`MulticastSocket::join`].
+    pub fn send_to(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        destination: &SocketAddrV4,
+    ) -> io::Result<usize> {
+        self.send_to_with(buf, interface, destination, &crate::SendOptions::default())
+    }
+
+    /// Sends the buffer to the multicast group, overriding socket options for this packet.
+    pub fn send_with(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        options: &crate::SendOptions,
+    ) -> io::Result<usize> {
+        self.send_to_with(buf, interface, &self.multicast_address, options)
+    }
+
+    /// Sends the buffer to the destination, overriding socket options for this packet.
+    pub fn send_to_with(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        destination: &SocketAddrV4,
+        options: &crate::SendOptions,
+    ) -> io::Result<usize> {
+        let destination = socket2::SockAddr::from(*destination);
+        let mut control = ControlBuffer::new();
+        let mut data = libc::iovec {
+            iov_base: buf.as_ptr() as *mut _,
+            iov_len: buf.len(),
+        };
+        let header = send_header(&destination, &mut data, &mut control, interface, options)
+            .io_context_on(Operation::Send, interface)?;
+
+        let sent = unsafe { libc::sendmsg(self.socket.as_raw_fd(), &header, 0) };
+        if sent < 0 {
+            return Err(io::Error::last_os_error()).io_context_on(Operation::Send, interface);
+        }
+        Ok(sent as usize)
+    }
+
+    /// Sends the buffer on every interface with at least one joined group.
+    ///
+    /// Failing interfaces are handled according to
+    /// [`MulticastOptions::broadcast_policy`](crate::MulticastOptions::broadcast_policy).
+    pub fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
+        match self.broadcast_policy {
+            crate::BroadcastPolicy::FailFast => {
+                for interface in self.joined_interfaces() {
+                    self.send(buf, &Interface::Ip(interface))?;
+                }
+                Ok(())
             }
+            crate::BroadcastPolicy::BestEffort => crate::best_effort(self.broadcast_report(buf)),
         }
+    }
 
-        Ok(Message {
-            data: data_buffer[0..message.bytes].to_vec(),
-            origin_address,
-            interface,
-        })
+    /// Sends the buffer on every interface with at least one joined group, reporting the
+    /// outcome of each one, regardless of the broadcast policy.
+    pub fn broadcast_report(&self, buf: &[u8]) -> Vec<crate::BroadcastResult> {
+        self.joined_interfaces()
+            .into_iter()
+            .map(|interface| {
+                let interface = Interface::Ip(interface);
+                crate::BroadcastResult {
+                    result: self.send(buf, &interface),
+                    interface,
+                }
+            })
+            .collect()
     }
 
-    pub fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
-        let mut pkt_info: libc::in_pktinfo = unsafe { mem::zeroed() };
+    /// Toggles the non-blocking mode, where [`MulticastSocket::receive`] and the send methods
+    /// return [`io::ErrorKind::WouldBlock`] instead of waiting.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+
+    /// The size of the kernel receive queue, as granted for
+    /// [`MulticastOptions::receive_buffer_size`](crate::MulticastOptions::receive_buffer_size).
+    ///
+    /// Linux reports twice the requested size, as it counts its own bookkeeping.
+    pub fn receive_buffer_size(&self) -> io::Result<usize> {
+        self.socket.recv_buffer_size()
+    }
+
+    /// The size of the kernel send queue, as granted for
+    /// [`MulticastOptions::send_buffer_size`](crate::MulticastOptions::send_buffer_size).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.socket.send_buffer_size()
+    }
 
-        match interface {
-            Interface::Default => {}
-            Interface::Ip(address) => pkt_info.ipi_spec_dst = sock::Ipv4Addr::from_std(address).0,
-            Interface::Index(index) => pkt_info.ipi_ifindex = *index as _,
+    /// The interfaces with at least one joined group, used by [`MulticastSocket::broadcast`].
+    pub fn active_interfaces(&self) -> Vec<Ipv4Addr> {
+        self.joined_interfaces()
+    }
+
+    /// The interfaces left out when the socket was created, because joining the group on them
+    /// failed.
+    ///
+    /// Only filled when
+    /// [`MulticastOptions::skip_failing_interfaces`](crate::MulticastOptions::skip_failing_interfaces)
+    /// is enabled.
+    pub fn skipped_interfaces(&self) -> &[crate::SkippedInterface] {
+        &self.skipped
+    }
+
+    fn joined_interfaces(&self) -> Vec<Ipv4Addr> {
+        let memberships = self.memberships.lock().unwrap();
+        let mut interfaces = Vec::with_capacity(memberships.len());
+        // An interface may have joined multiple groups, but must only be sent to once
+        for membership in memberships.iter() {
+            if !interfaces.contains(&membership.interface) {
+                interfaces.push(membership.interface);
+            }
+        }
+        interfaces
+    }
+}
+
+impl MulticastSocket {
+    /// Joins the group on the interface, without rebinding the socket.
+    ///
+    /// The interface is included on the next [`MulticastSocket::broadcast`] calls. Packets of
+    /// every joined group are received by the same socket, as long as they are sent to the
+    /// port it is bound to, and can be told apart by [`Message::destination_address`].
+    ///
+    /// The group of the socket is only joined for the
+    /// [`MulticastOptions::sources`](crate::MulticastOptions::sources), when any are listed.
+    pub fn join(&self, group: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        let sources: &[Ipv4Addr] = if group == self.multicast_address.ip() {
+            &self.sources
+        } else {
+            &[]
         };
+        let mut memberships = self.memberships.lock().unwrap();
+        join_interface(&self.socket, group, sources, interface)
+            .io_context_on(Operation::Join, &Interface::Ip(*interface))?;
+        memberships.extend(memberships_on(group, sources, interface));
+        Ok(())
+    }
 
-        let destination = sock::InetAddr::from_std(&self.multicast_address.into());
+    /// Leaves the group on the interface, without rebinding the socket.
+    ///
+    /// Every source joined on the interface for the group is left as well. Once the interface
+    /// has no joined groups left, it is no longer used by [`MulticastSocket::broadcast`].
+    pub fn leave(&self, group: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        self.socket
+            .leave_multicast_v4(group, interface)
+            .io_context_on(Operation::Leave, &Interface::Ip(*interface))?;
+        memberships.retain(|m| m.group != *group || m.interface != *interface);
+        Ok(())
+    }
+}
 
-        sock::sendmsg(
-            self.socket.as_raw_fd(),
-            &[IoVec::from_slice(&buf)],
-            &[sock::ControlMessage::Ipv4PacketInfo(&pkt_info)],
-            sock::MsgFlags::empty(),
-            Some(&sock::SockAddr::new_inet(destination)),
+impl MulticastSocket {
+    /// Joins the group on the interface, accepting only packets sent by `source`.
+    ///
+    /// Translates to `IP_ADD_SOURCE_MEMBERSHIP`, and can be called multiple times to accept
+    /// multiple senders. Like [`MulticastSocket::join`], the interface is included on the next
+    /// [`MulticastSocket::broadcast`] calls.
+    pub fn join_source_group(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        set_source_membership(
+            &self.socket,
+            libc::IP_ADD_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
         )
-        .map_err(nix_to_io_error)
+        .io_context_on(Operation::Join, &Interface::Ip(*interface))?;
+        memberships.push(Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        });
+        Ok(())
     }
 
-    pub fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
-        for interface in &self.interfaces {
-            self.send(buf, &Interface::Ip(*interface))?;
-        }
+    /// Stops accepting packets from `source`, undoing [`MulticastSocket::join_source_group`].
+    pub fn leave_source_group(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        set_source_membership(
+            &self.socket,
+            libc::IP_DROP_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(Operation::Leave, &Interface::Ip(*interface))?;
+        let membership = Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        };
+        memberships.retain(|m| *m != membership);
         Ok(())
     }
+
+    /// Ignores packets sent by `source` on a group joined for any source.
+    ///
+    /// Translates to `IP_BLOCK_SOURCE`, which requires the group to already be joined on the
+    /// interface without source filters.
+    pub fn block_source(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        set_source_membership(
+            &self.socket,
+            libc::IP_BLOCK_SOURCE,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(
+            Operation::SetOption("IP_BLOCK_SOURCE"),
+            &Interface::Ip(*interface),
+        )
+    }
+
+    /// Accepts packets from `source` again, undoing [`MulticastSocket::block_source`].
+    pub fn unblock_source(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        set_source_membership(
+            &self.socket,
+            libc::IP_UNBLOCK_SOURCE,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(
+            Operation::SetOption("IP_UNBLOCK_SOURCE"),
+            &Interface::Ip(*interface),
+        )
+    }
 }
@@ -0,0 +1,118 @@
+use std::io;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::ptr;
+
+use super::{
+    message_meta, receive_header, send_header, ControlBuffer, Interface, MessageMeta,
+    MulticastSocket,
+};
+use crate::error::{Context, Operation};
+
+impl MulticastSocket {
+    /// Receives up to one packet per buffer with a single `recvmmsg` call.
+    ///
+    /// Waits for the first packet like [`MulticastSocket::receive_into`], then collects the
+    /// packets that are already queued without waiting further. The returned details are in
+    /// the same order as the buffers the packets were written to.
+    ///
+    /// [`MulticastOptions::min_ttl`](crate::MulticastOptions::min_ttl) is not applied, so that
+    /// every buffer keeps its packet. Check [`MessageMeta::ttl`] instead.
+    pub fn receive_batch(&self, buffers: &mut [&mut [u8]]) -> io::Result<Vec<MessageMeta>> {
+        if buffers.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut origins: Vec<libc::sockaddr_in> = vec![unsafe { mem::zeroed() }; buffers.len()];
+        let mut controls: Vec<ControlBuffer> =
+            buffers.iter().map(|_| ControlBuffer::new()).collect();
+        let mut data: Vec<libc::iovec> = buffers
+            .iter_mut()
+            .map(|buffer| libc::iovec {
+                iov_base: buffer.as_mut_ptr() as *mut _,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut headers: Vec<libc::mmsghdr> = origins
+            .iter_mut()
+            .zip(data.iter_mut())
+            .zip(controls.iter_mut())
+            .map(|((origin, data), control)| libc::mmsghdr {
+                msg_hdr: receive_header(origin, data, control),
+                msg_len: 0,
+            })
+            .collect();
+
+        let received = unsafe {
+            libc::recvmmsg(
+                self.socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                headers.len() as _,
+                libc::MSG_WAITFORONE as _,
+                ptr::null_mut(),
+            )
+        };
+        if received < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(headers[..received as usize]
+            .iter()
+            .zip(origins.iter())
+            .map(|(header, origin)| message_meta(&header.msg_hdr, origin, header.msg_len as _))
+            .collect())
+    }
+
+    /// Sends every buffer to the multicast group with a single `sendmmsg` call, each through
+    /// its own interface.
+    ///
+    /// Returns how many packets were sent, which may be less than requested if the socket
+    /// buffer fills up. The remaining packets can be sent with another call.
+    pub fn send_batch(&self, packets: &[(&[u8], Interface)]) -> io::Result<usize> {
+        if packets.is_empty() {
+            return Ok(0);
+        }
+
+        let destination = socket2::SockAddr::from(self.multicast_address);
+        let mut controls: Vec<ControlBuffer> =
+            packets.iter().map(|_| ControlBuffer::new()).collect();
+        let mut data: Vec<libc::iovec> = packets
+            .iter()
+            .map(|(buffer, _)| libc::iovec {
+                iov_base: buffer.as_ptr() as *mut _,
+                iov_len: buffer.len(),
+            })
+            .collect();
+        let mut headers: Vec<libc::mmsghdr> = packets
+            .iter()
+            .zip(data.iter_mut())
+            .zip(controls.iter_mut())
+            .map(|(((_, interface), data), control)| {
+                Ok(libc::mmsghdr {
+                    msg_hdr: send_header(
+                        &destination,
+                        data,
+                        control,
+                        interface,
+                        &crate::SendOptions::default(),
+                    )
+                    .io_context_on(Operation::Send, interface)?,
+                    msg_len: 0,
+                })
+            })
+            .collect::<io::Result<_>>()?;
+
+        let sent = unsafe {
+            libc::sendmmsg(
+                self.socket.as_raw_fd(),
+                headers.as_mut_ptr(),
+                headers.len() as _,
+                0,
+            )
+        };
+        if sent < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(sent as usize)
+    }
+}
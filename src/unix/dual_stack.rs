@@ -0,0 +1,225 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV4, SocketAddrV6};
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+use nix::poll::{poll, PollFd, PollFlags};
+
+use super::{
+    nix_to_io_error, poll_timeout, read_timeout_expired, Interface, Message, MulticastSocket,
+    MulticastSocketV6,
+};
+
+/// A pair of IPv4 and IPv6 sockets, served through a single receive and broadcast API.
+pub struct DualStackMulticastSocket {
+    v4: MulticastSocket,
+    v6: MulticastSocketV6,
+    read_timeout: Option<Duration>,
+    min_ttl: Option<u8>,
+    broadcast_policy: crate::BroadcastPolicy,
+    // Alternates which family is read first when both have pending packets, so a busy family
+    // cannot starve the other one.
+    prefer_v6: AtomicBool,
+}
+
+#[derive(Debug, Clone)]
+pub struct DualStackMessage {
+    pub data: Vec<u8>,
+    pub origin_address: SocketAddr,
+    pub interface: Interface,
+    /// The address the packet was sent to, identifying the group it belongs to.
+    pub destination_address: IpAddr,
+    /// See [`Message::interface_address`](super::Message::interface_address).
+    ///
+    /// IPv6 packets only report the interface index, so this is the unspecified IPv6 address
+    /// for them.
+    pub interface_address: IpAddr,
+    /// See [`Message::truncated`](super::Message::truncated).
+    pub truncated: bool,
+    /// See [`Message::tos`](super::Message::tos), or the traffic class of IPv6 packets.
+    pub tos: Option<u8>,
+    /// See [`Message::ttl`](super::Message::ttl), or the hop limit of IPv6 packets.
+    pub ttl: Option<u8>,
+    /// See [`Message::received_at`](super::Message::received_at).
+    pub received_at: Option<SystemTime>,
+}
+
+impl DualStackMulticastSocket {
+    pub fn all_interfaces(
+        multicast_address_v4: SocketAddrV4,
+        multicast_address_v6: SocketAddrV6,
+    ) -> io::Result<Self> {
+        let options = crate::MulticastOptions::default();
+        Ok(DualStackMulticastSocket {
+            read_timeout: options.read_timeout,
+            min_ttl: options.min_ttl,
+            broadcast_policy: options.broadcast_policy,
+            v4: MulticastSocket::all_interfaces(multicast_address_v4)?,
+            v6: MulticastSocketV6::all_interfaces(multicast_address_v6)?,
+            prefer_v6: AtomicBool::new(false),
+        })
+    }
+
+    pub fn with_options(
+        multicast_address_v4: SocketAddrV4,
+        interfaces_v4: Vec<Ipv4Addr>,
+        multicast_address_v6: SocketAddrV6,
+        interfaces_v6: Vec<u32>,
+        options: crate::MulticastOptions,
+    ) -> io::Result<Self> {
+        Ok(DualStackMulticastSocket {
+            read_timeout: options.read_timeout,
+            min_ttl: options.min_ttl,
+            broadcast_policy: options.broadcast_policy,
+            v4: MulticastSocket::with_options(
+                multicast_address_v4,
+                interfaces_v4,
+                options.clone(),
+            )?,
+            v6: MulticastSocketV6::with_options(multicast_address_v6, interfaces_v6, options)?,
+            prefer_v6: AtomicBool::new(false),
+        })
+    }
+
+    /// Access the underlying IPv4 socket.
+    pub fn v4(&self) -> &MulticastSocket {
+        &self.v4
+    }
+
+    /// Access the underlying IPv6 socket.
+    pub fn v6(&self) -> &MulticastSocketV6 {
+        &self.v6
+    }
+}
+
+impl DualStackMulticastSocket {
+    /// Waits for a packet on either family, honoring the `read_timeout` of the options.
+    ///
+    /// When the timeout expires, an error of kind [`io::ErrorKind::WouldBlock`] is returned,
+    /// matching what the single-family sockets report. Packets below
+    /// [`MulticastOptions::min_ttl`](crate::MulticastOptions::min_ttl) are dropped, and both
+    /// families are waited on again, within the same timeout.
+    pub fn receive(&self) -> io::Result<DualStackMessage> {
+        let started = Instant::now();
+        loop {
+            let timeout = match self.read_timeout {
+                Some(timeout) => {
+                    let remaining = (started + timeout).saturating_duration_since(Instant::now());
+                    if remaining == Duration::from_secs(0) {
+                        return Err(read_timeout_expired());
+                    }
+                    poll_timeout(remaining)
+                }
+                None => -1,
+            };
+
+            let mut fds = [
+                PollFd::new(self.v4.as_raw_fd(), PollFlags::POLLIN),
+                PollFd::new(self.v6.as_raw_fd(), PollFlags::POLLIN),
+            ];
+            let ready = poll(&mut fds, timeout).map_err(nix_to_io_error)?;
+            if ready == 0 {
+                return Err(read_timeout_expired());
+            }
+
+            let is_ready = |fd: &PollFd| fd.revents().map_or(false, |r| !r.is_empty());
+            let v4_ready = is_ready(&fds[0]);
+            let v6_ready = is_ready(&fds[1]);
+            let read_v6 = match (v4_ready, v6_ready) {
+                (true, true) => self.prefer_v6.fetch_xor(true, Ordering::Relaxed),
+                (_, v6_ready) => v6_ready,
+            };
+
+            // A single attempt that cannot block, so a packet dropped for its TTL, or taken by
+            // another thread, does not stop the other family from being read
+            let received = if read_v6 {
+                self.receive_v6()
+            } else {
+                self.receive_v4()
+            };
+            match received {
+                Ok(message) => match (self.min_ttl, message.ttl) {
+                    (Some(min_ttl), Some(ttl)) if ttl < min_ttl => {}
+                    _ => return Ok(message),
+                },
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn receive_v4(&self) -> io::Result<DualStackMessage> {
+        let mut data = vec![0; self.v4.buffer_size];
+        let meta = self.v4.receive_any(&mut data, libc::MSG_DONTWAIT)?;
+        data.truncate(meta.len);
+        let message = Message::from_meta(data, meta);
+        Ok(DualStackMessage {
+            data: message.data,
+            origin_address: message.origin_address.into(),
+            interface: message.interface,
+            destination_address: message.destination_address.into(),
+            interface_address: message.interface_address.into(),
+            truncated: message.truncated,
+            tos: message.tos,
+            ttl: message.ttl,
+            received_at: message.received_at,
+        })
+    }
+
+    fn receive_v6(&self) -> io::Result<DualStackMessage> {
+        let message = self.v6.receive_any(libc::MSG_DONTWAIT)?;
+        Ok(DualStackMessage {
+            data: message.data,
+            origin_address: message.origin_address.into(),
+            interface: message.interface,
+            destination_address: message.destination_address.into(),
+            interface_address: Ipv6Addr::UNSPECIFIED.into(),
+            truncated: message.truncated,
+            tos: message.traffic_class,
+            ttl: message.hop_limit,
+            received_at: message.received_at,
+        })
+    }
+
+    /// Sends the buffer to the IPv4 group.
+    pub fn send_v4(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        self.v4.send(buf, interface)
+    }
+
+    /// Sends the buffer to the IPv6 group.
+    pub fn send_v6(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        self.v6.send(buf, interface)
+    }
+
+    /// Sends the buffer to the group of the same family, and through the same interface, as
+    /// the one the message was received on.
+    pub fn reply(&self, buf: &[u8], message: &DualStackMessage) -> io::Result<usize> {
+        match message.origin_address {
+            SocketAddr::V4(_) => self.v4.send(buf, &message.interface),
+            SocketAddr::V6(_) => self.v6.send(buf, &message.interface),
+        }
+    }
+
+    /// Sends the buffer on every interface of both families.
+    ///
+    /// With [`BroadcastPolicy::BestEffort`](crate::BroadcastPolicy::BestEffort), it only fails
+    /// when no interface of either family succeeded.
+    pub fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
+        match self.broadcast_policy {
+            crate::BroadcastPolicy::FailFast => {
+                self.v4.broadcast(buf)?;
+                self.v6.broadcast(buf)
+            }
+            crate::BroadcastPolicy::BestEffort => crate::best_effort(self.broadcast_report(buf)),
+        }
+    }
+
+    /// Sends the buffer on every interface of both families, reporting the outcome of each
+    /// one.
+    pub fn broadcast_report(&self, buf: &[u8]) -> Vec<crate::BroadcastResult> {
+        let mut report = self.v4.broadcast_report(buf);
+        report.extend(self.v6.broadcast_report(buf));
+        report
+    }
+}
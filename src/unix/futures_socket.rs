@@ -0,0 +1,115 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+use futures_core::Stream;
+use futures_sink::Sink;
+
+use super::{Interface, Message, MulticastSocket};
+
+/// A [`MulticastSocket`] driven by the `async-io` reactor, usable from any executor.
+///
+/// Received packets are read as a [`Stream`] of messages, and packets are sent through it as a
+/// [`Sink`] of buffers and the interface to send them on. Like `AsyncMulticastSocket`, the
+/// socket is switched to non-blocking mode, so the `read_timeout` of the options has no effect.
+pub struct MulticastFramed {
+    inner: Async<MulticastSocket>,
+    pending: Option<(Vec<u8>, Interface)>,
+}
+
+impl MulticastFramed {
+    /// Registers an existing socket with the `async-io` reactor.
+    pub fn new(socket: MulticastSocket) -> io::Result<Self> {
+        Ok(MulticastFramed {
+            inner: Async::new(socket)?,
+            pending: None,
+        })
+    }
+
+    pub fn all_interfaces(multicast_address: SocketAddrV4) -> io::Result<Self> {
+        Self::new(MulticastSocket::all_interfaces(multicast_address)?)
+    }
+
+    pub fn with_options(
+        multicast_address: SocketAddrV4,
+        interfaces: Vec<Ipv4Addr>,
+        options: crate::MulticastOptions,
+    ) -> io::Result<Self> {
+        Self::new(MulticastSocket::with_options(
+            multicast_address,
+            interfaces,
+            options,
+        )?)
+    }
+
+    /// Access the underlying socket, for example to join or leave groups.
+    pub fn get_ref(&self) -> &MulticastSocket {
+        self.inner.get_ref()
+    }
+
+    fn poll_send_pending(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while let Some((buf, interface)) = &self.pending {
+            match self.inner.get_ref().send(buf, interface) {
+                Ok(_) => self.pending = None,
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    match self.inner.poll_writable(cx) {
+                        Poll::Ready(Ok(())) => continue,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                Err(e) => {
+                    self.pending = None;
+                    return Poll::Ready(Err(e));
+                }
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for MulticastFramed {
+    type Item = io::Result<Message>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            match self.inner.get_ref().receive() {
+                Err(e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    match self.inner.poll_readable(cx) {
+                        Poll::Ready(Ok(())) => continue,
+                        Poll::Ready(Err(e)) => return Poll::Ready(Some(Err(e))),
+                        Poll::Pending => return Poll::Pending,
+                    }
+                }
+                result => return Poll::Ready(Some(result)),
+            }
+        }
+    }
+}
+
+/// Each item is sent to the group of the socket, through the given interface.
+///
+/// Only one packet is buffered at a time: [`Sink::poll_ready`] waits until the previous one was
+/// handed to the kernel, giving back-pressure when the socket send buffer is full.
+impl Sink<(Vec<u8>, Interface)> for MulticastFramed {
+    type Error = io::Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (Vec<u8>, Interface)) -> io::Result<()> {
+        self.get_mut().pending = Some(item);
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+}
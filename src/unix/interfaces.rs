@@ -0,0 +1,77 @@
+use std::io;
+use std::net::{IpAddr, Ipv4Addr};
+
+use nix::ifaddrs::getifaddrs;
+use nix::net::if_::{if_nametoindex, InterfaceFlags};
+use nix::sys::socket::SockAddr;
+
+use super::nix_to_io_error;
+use crate::{InterfaceAddress, InterfaceInfo};
+
+/// Lists every interface of the host, with or without IPv4 addresses.
+///
+/// Interfaces removed while they are being listed are left out.
+pub fn discover_interfaces() -> io::Result<Vec<InterfaceInfo>> {
+    let mut interfaces: Vec<InterfaceInfo> = Vec::new();
+
+    for entry in getifaddrs().map_err(nix_to_io_error)? {
+        // Address aliases are listed with labels such as `eth0:1`, sharing the index of the
+        // interface they belong to
+        let index = if_nametoindex(entry.interface_name.as_str()).or_else(|_| {
+            let name = entry.interface_name.split(':').next().unwrap_or_default();
+            if_nametoindex(name)
+        });
+        // The interface was removed after being listed, which is common while hotplugging
+        let index = match index {
+            Ok(index) => index,
+            Err(_) => continue,
+        };
+
+        let position = match interfaces.iter().position(|i| i.index == index) {
+            Some(position) => position,
+            None => {
+                let flags = entry.flags;
+                interfaces.push(InterfaceInfo {
+                    name: entry.interface_name.clone(),
+                    index,
+                    addresses: Vec::new(),
+                    up: flags.contains(InterfaceFlags::IFF_UP),
+                    running: flags.contains(InterfaceFlags::IFF_RUNNING),
+                    multicast: flags.contains(InterfaceFlags::IFF_MULTICAST),
+                    loopback: flags.contains(InterfaceFlags::IFF_LOOPBACK),
+                    point_to_point: flags.contains(InterfaceFlags::IFF_POINTOPOINT),
+                });
+                interfaces.len() - 1
+            }
+        };
+
+        if let Some(address) = entry.address.as_ref().and_then(to_ipv4) {
+            let broadcast = if entry.flags.contains(InterfaceFlags::IFF_BROADCAST) {
+                entry.broadcast.as_ref().and_then(to_ipv4)
+            } else {
+                None
+            };
+            interfaces[position].addresses.push(InterfaceAddress {
+                address,
+                netmask: entry
+                    .netmask
+                    .as_ref()
+                    .and_then(to_ipv4)
+                    .unwrap_or(Ipv4Addr::UNSPECIFIED),
+                broadcast,
+            });
+        }
+    }
+
+    Ok(interfaces)
+}
+
+fn to_ipv4(address: &SockAddr) -> Option<Ipv4Addr> {
+    match address {
+        SockAddr::Inet(inet) => match inet.ip().to_std() {
+            IpAddr::V4(v4) => Some(v4),
+            IpAddr::V6(_) => None,
+        },
+        _ => None,
+    }
+}
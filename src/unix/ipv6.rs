@@ -0,0 +1,385 @@
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::net::{Ipv6Addr, SocketAddr, SocketAddrV6};
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::ptr;
+use std::time::{Instant, SystemTime};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use nix::sys::socket as sock;
+use nix::sys::uio::IoVec;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use super::read_timestamp;
+use super::{
+    name_to_index, nix_to_io_error, receive_header, set_buffer_sizes, set_int_option, set_priority,
+    set_timestamping, wait_for_next, ControlBuffer, Interface,
+};
+use crate::error::{Context, Operation};
+
+fn create_on_interfaces(
+    options: crate::MulticastOptions,
+    interfaces: Vec<u32>,
+    multicast_address: SocketAddrV6,
+) -> Result<MulticastSocketV6, crate::Error> {
+    // `sources` only holds IPv4 senders, so the group would be joined for any source
+    if !options.sources.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "source-specific joins are only supported on IPv4 sockets",
+        ))
+        .context(Operation::Join);
+    }
+
+    let socket = Socket::new(Domain::ipv6(), Type::dgram(), Some(Protocol::udp()))
+        .context(Operation::CreateSocket)?;
+    socket
+        .set_read_timeout(options.read_timeout)
+        .context(Operation::SetOption("SO_RCVTIMEO"))?;
+    socket
+        .set_multicast_loop_v6(options.loopback)
+        .context(Operation::SetOption("IPV6_MULTICAST_LOOP"))?;
+    if let Some(hops) = options.ttl {
+        socket
+            .set_multicast_hops_v6(hops)
+            .context(Operation::SetOption("IPV6_MULTICAST_HOPS"))?;
+    }
+    if let Some(class) = options.tos {
+        set_int_option(&socket, libc::IPPROTO_IPV6, libc::IPV6_TCLASS, class as _)
+            .context(Operation::SetOption("IPV6_TCLASS"))?;
+    }
+    if let Some(priority) = options.priority {
+        set_priority(&socket, priority).context(Operation::SetOption("SO_PRIORITY"))?;
+    }
+    socket
+        .set_reuse_address(options.reuse_address)
+        .context(Operation::SetOption("SO_REUSEADDR"))?;
+    socket
+        .set_reuse_port(options.reuse_port)
+        .context(Operation::SetOption("SO_REUSEPORT"))?;
+    set_buffer_sizes(&socket, &options)?;
+    // Keep the socket away from IPv4-mapped traffic, so it can share the port with an IPv4
+    // `MulticastSocket`.
+    socket
+        .set_only_v6(true)
+        .context(Operation::SetOption("IPV6_V6ONLY"))?;
+
+    // Ipv6RecvPacketInfo translates to `IPV6_RECVPKTINFO`. Checkout the [ipv6
+    // manpage](https://man7.org/linux/man-pages/man7/ipv6.7.html) for more details. Like its
+    // IPv4 counterpart, it allows for determining on which interface a packet was received.
+    sock::setsockopt(socket.as_raw_fd(), sock::sockopt::Ipv6RecvPacketInfo, &true)
+        .map_err(nix_to_io_error)
+        .context(Operation::SetOption("IPV6_RECVPKTINFO"))?;
+    // Reports the traffic class and hop limit of received packets, the IPv6 counterparts of
+    // the type of service and TTL
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    set_int_option(&socket, libc::IPPROTO_IPV6, libc::IPV6_RECVTCLASS, 1)
+        .context(Operation::SetOption("IPV6_RECVTCLASS"))?;
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    set_int_option(&socket, libc::IPPROTO_IPV6, libc::IPV6_RECVHOPLIMIT, 1)
+        .context(Operation::SetOption("IPV6_RECVHOPLIMIT"))?;
+    set_timestamping(&socket, options.timestamping)?;
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    if options.min_ttl.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "received hop limits are only reported on Linux",
+        ))
+        .context(Operation::SetOption("IPV6_RECVHOPLIMIT"));
+    }
+
+    // IPv6 memberships are identified by the interface index instead of an address
+    let mut joined = Vec::with_capacity(interfaces.len());
+    let mut skipped = Vec::new();
+    for interface in interfaces {
+        let result = socket
+            .join_multicast_v6(multicast_address.ip(), interface)
+            .context_on(Operation::Join, &Interface::Index(interface as _));
+        match result {
+            Ok(()) => joined.push(interface),
+            Err(error) if options.skip_failing_interfaces => {
+                skipped.push(crate::SkippedInterface {
+                    interface: Interface::Index(interface as _),
+                    error,
+                })
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    if joined.is_empty() && !skipped.is_empty() {
+        return Err(skipped.remove(0).error);
+    }
+
+    socket
+        .bind(&SocketAddr::new(options.bind_address_v6.into(), multicast_address.port()).into())
+        .context(Operation::Bind)?;
+
+    Ok(MulticastSocketV6 {
+        socket,
+        interfaces: joined,
+        multicast_address,
+        buffer_size: options.buffer_size,
+        broadcast_policy: options.broadcast_policy,
+        min_ttl: options.min_ttl,
+        skipped,
+    })
+}
+
+pub struct MulticastSocketV6 {
+    socket: socket2::Socket,
+    interfaces: Vec<u32>,
+    multicast_address: SocketAddrV6,
+    buffer_size: usize,
+    broadcast_policy: crate::BroadcastPolicy,
+    min_ttl: Option<u8>,
+    skipped: Vec<crate::SkippedInterface>,
+}
+
+#[derive(Debug, Clone)]
+pub struct MessageV6 {
+    pub data: Vec<u8>,
+    pub origin_address: SocketAddrV6,
+    pub interface: Interface,
+    /// The address the packet was sent to, identifying the group it belongs to.
+    pub destination_address: Ipv6Addr,
+    /// See [`Message::truncated`](super::Message::truncated).
+    pub truncated: bool,
+    /// The traffic class of the packet, the IPv6 counterpart of
+    /// [`Message::tos`](super::Message::tos), only reported on Linux.
+    pub traffic_class: Option<u8>,
+    /// The hop limit the packet arrived with, the IPv6 counterpart of
+    /// [`Message::ttl`](super::Message::ttl), only reported on Linux.
+    pub hop_limit: Option<u8>,
+    /// See [`Message::received_at`](super::Message::received_at).
+    pub received_at: Option<SystemTime>,
+}
+
+/// Lists the index of every non-loopback interface with an IPv6 address.
+pub fn all_ipv6_interfaces() -> io::Result<Vec<u32>> {
+    let interfaces = if_addrs::get_if_addrs()?.into_iter();
+
+    // Same as IPv4, an interface with multiple ips must only be joined once
+    let mut collected_interfaces = HashMap::with_capacity(interfaces.len());
+    for interface in interfaces {
+        match (interface.ip(), interface.index) {
+            (std::net::IpAddr::V6(_), Some(index)) if !interface.is_loopback() => {
+                collected_interfaces.entry(interface.name).or_insert(index);
+            }
+            _ => {}
+        }
+    }
+    Ok(collected_interfaces.into_values().collect())
+}
+
+impl MulticastSocketV6 {
+    pub fn all_interfaces(multicast_address: SocketAddrV6) -> io::Result<Self> {
+        let interfaces = all_ipv6_interfaces().context(Operation::DiscoverInterfaces)?;
+        Ok(create_on_interfaces(
+            Default::default(),
+            interfaces,
+            multicast_address,
+        )?)
+    }
+
+    pub fn with_options(
+        multicast_address: SocketAddrV6,
+        interfaces: Vec<u32>,
+        options: crate::MulticastOptions,
+    ) -> io::Result<Self> {
+        Ok(create_on_interfaces(
+            options,
+            interfaces,
+            multicast_address,
+        )?)
+    }
+}
+
+impl MulticastSocketV6 {
+    /// The indexes of the interfaces the group was joined on.
+    pub fn active_interfaces(&self) -> &[u32] {
+        &self.interfaces
+    }
+
+    /// See [`MulticastSocket::skipped_interfaces`](super::MulticastSocket::skipped_interfaces).
+    pub fn skipped_interfaces(&self) -> &[crate::SkippedInterface] {
+        &self.skipped
+    }
+
+    /// See [`MulticastSocket::receive_buffer_size`](super::MulticastSocket::receive_buffer_size).
+    pub fn receive_buffer_size(&self) -> io::Result<usize> {
+        self.socket.recv_buffer_size()
+    }
+
+    /// See [`MulticastSocket::send_buffer_size`](super::MulticastSocket::send_buffer_size).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.socket.send_buffer_size()
+    }
+}
+
+impl AsRawFd for MulticastSocketV6 {
+    fn as_raw_fd(&self) -> RawFd {
+        self.socket.as_raw_fd()
+    }
+}
+
+fn parse_control_messages(header: &libc::msghdr, message: &mut MessageV6) {
+    let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(header) };
+    while !cmsg.is_null() {
+        let (level, kind) = unsafe { ((*cmsg).cmsg_level, (*cmsg).cmsg_type) };
+        match (level, kind) {
+            (libc::IPPROTO_IPV6, libc::IPV6_PKTINFO) => {
+                let pktinfo: libc::in6_pktinfo =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                message.interface = Interface::Index(pktinfo.ipi6_ifindex as _);
+                message.destination_address = Ipv6Addr::from(pktinfo.ipi6_addr.s6_addr);
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::IPPROTO_IPV6, libc::IPV6_TCLASS) => {
+                let class: libc::c_int =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                message.traffic_class = Some(class as u8);
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::IPPROTO_IPV6, libc::IPV6_HOPLIMIT) => {
+                let hop_limit: libc::c_int =
+                    unsafe { ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const _) };
+                message.hop_limit = Some(hop_limit as u8);
+            }
+            #[cfg(any(target_os = "linux", target_os = "android"))]
+            (libc::SOL_SOCKET, _) => {
+                message.received_at = read_timestamp(cmsg).or(message.received_at)
+            }
+            _ => {}
+        }
+        cmsg = unsafe { libc::CMSG_NXTHDR(header, cmsg) };
+    }
+}
+
+impl MulticastSocketV6 {
+    /// Receives a packet, dropping the ones below
+    /// [`MulticastOptions::min_ttl`](crate::MulticastOptions::min_ttl) by their hop limit.
+    pub fn receive(&self) -> io::Result<MessageV6> {
+        let min_ttl = match self.min_ttl {
+            Some(min_ttl) => min_ttl,
+            None => return self.receive_any(0),
+        };
+
+        let started = Instant::now();
+        loop {
+            let message = self.receive_any(0)?;
+            match message.hop_limit {
+                Some(hop_limit) if hop_limit < min_ttl => wait_for_next(&self.socket, started)?,
+                _ => return Ok(message),
+            }
+        }
+    }
+
+    /// Receives a packet regardless of its hop limit, with the `recvmsg` flags, such as
+    /// `MSG_DONTWAIT`.
+    pub(super) fn receive_any(&self, flags: libc::c_int) -> io::Result<MessageV6> {
+        let mut data_buffer = vec![0; self.buffer_size];
+        let mut origin: libc::sockaddr_in6 = unsafe { mem::zeroed() };
+        let mut control = ControlBuffer::new();
+        let mut data = libc::iovec {
+            iov_base: data_buffer.as_mut_ptr() as *mut _,
+            iov_len: data_buffer.len(),
+        };
+        let mut header = receive_header(&mut origin, &mut data, &mut control);
+
+        let read = unsafe { libc::recvmsg(self.socket.as_raw_fd(), &mut header, flags) };
+        if read < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        data_buffer.truncate(read as usize);
+
+        let origin_address = if origin.sin6_family as libc::c_int == libc::AF_INET6 {
+            SocketAddrV6::new(
+                Ipv6Addr::from(origin.sin6_addr.s6_addr),
+                u16::from_be(origin.sin6_port),
+                origin.sin6_flowinfo,
+                origin.sin6_scope_id,
+            )
+        } else {
+            SocketAddrV6::new(Ipv6Addr::UNSPECIFIED, 0, 0, 0)
+        };
+
+        let mut message = MessageV6 {
+            data: data_buffer,
+            origin_address,
+            interface: Interface::Default,
+            destination_address: Ipv6Addr::UNSPECIFIED,
+            truncated: header.msg_flags & libc::MSG_TRUNC != 0,
+            traffic_class: None,
+            hop_limit: None,
+            received_at: None,
+        };
+        parse_control_messages(&header, &mut message);
+        Ok(message)
+    }
+
+    /// Sends the buffer to the multicast group.
+    ///
+    /// IPv6 selects outgoing interfaces by index, so [`Interface::Ip`] is rejected with
+    /// [`io::ErrorKind::InvalidInput`].
+    pub fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        let mut pkt_info: libc::in6_pktinfo = unsafe { mem::zeroed() };
+
+        match interface {
+            Interface::Default => {}
+            Interface::Ip(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "IPv6 multicast requires an interface index",
+                ))
+            }
+            Interface::Index(index) => pkt_info.ipi6_ifindex = *index as _,
+            Interface::Name(name) => {
+                pkt_info.ipi6_ifindex =
+                    name_to_index(name).io_context_on(Operation::Send, interface)? as _
+            }
+        };
+
+        let destination = sock::InetAddr::from_std(&self.multicast_address.into());
+
+        sock::sendmsg(
+            self.socket.as_raw_fd(),
+            &[IoVec::from_slice(buf)],
+            &[sock::ControlMessage::Ipv6PacketInfo(&pkt_info)],
+            sock::MsgFlags::empty(),
+            Some(&sock::SockAddr::new_inet(destination)),
+        )
+        .map_err(nix_to_io_error)
+        .io_context_on(Operation::Send, interface)
+    }
+
+    /// Sends the buffer on every joined interface, handling failures according to
+    /// [`MulticastOptions::broadcast_policy`](crate::MulticastOptions::broadcast_policy).
+    pub fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
+        match self.broadcast_policy {
+            crate::BroadcastPolicy::FailFast => {
+                for interface in &self.interfaces {
+                    self.send(buf, &Interface::Index(*interface as _))?;
+                }
+                Ok(())
+            }
+            crate::BroadcastPolicy::BestEffort => crate::best_effort(self.broadcast_report(buf)),
+        }
+    }
+
+    /// Sends the buffer on every joined interface, reporting the outcome of each one,
+    /// regardless of the broadcast policy.
+    pub fn broadcast_report(&self, buf: &[u8]) -> Vec<crate::BroadcastResult> {
+        self.interfaces
+            .iter()
+            .map(|interface| {
+                let interface = Interface::Index(*interface as _);
+                crate::BroadcastResult {
+                    result: self.send(buf, &interface),
+                    interface,
+                }
+            })
+            .collect()
+    }
+}
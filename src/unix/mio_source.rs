@@ -0,0 +1,38 @@
+use std::io;
+use std::os::unix::io::AsRawFd;
+
+use ::mio::event::Source;
+use ::mio::unix::SourceFd;
+use ::mio::{Interest, Registry, Token};
+
+use super::MulticastSocket;
+
+/// Allows polling the socket alongside other mio sources.
+///
+/// Registering switches the socket to non-blocking mode, so [`MulticastSocket::receive`] and
+/// the send methods return [`io::ErrorKind::WouldBlock`] once there is nothing left to read, or
+/// no room left to write.
+impl Source for MulticastSocket {
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        self.set_nonblocking(true)?;
+        SourceFd(&self.as_raw_fd()).register(registry, token, interests)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interests: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interests)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
@@ -0,0 +1,127 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddrV4};
+
+use ::tokio::io::unix::AsyncFd;
+
+use super::{Interface, Message, MulticastSocket};
+
+/// A [`MulticastSocket`] registered with the Tokio reactor.
+///
+/// The socket is switched to non-blocking mode, so the `read_timeout` of the options has no
+/// effect. Use `tokio::time::timeout` around [`AsyncMulticastSocket::receive`] instead.
+pub struct AsyncMulticastSocket {
+    inner: AsyncFd<MulticastSocket>,
+}
+
+impl AsyncMulticastSocket {
+    /// Registers an existing socket with the Tokio reactor of the current runtime.
+    pub fn new(socket: MulticastSocket) -> io::Result<Self> {
+        socket.set_nonblocking(true)?;
+        // The socket owns its file descriptor, which stays open until it is dropped along with
+        // the AsyncFd
+        let inner = unsafe { AsyncFd::register(socket)? };
+        Ok(AsyncMulticastSocket { inner })
+    }
+
+    pub fn all_interfaces(multicast_address: SocketAddrV4) -> io::Result<Self> {
+        Self::new(MulticastSocket::all_interfaces(multicast_address)?)
+    }
+
+    pub fn with_options(
+        multicast_address: SocketAddrV4,
+        interfaces: Vec<Ipv4Addr>,
+        options: crate::MulticastOptions,
+    ) -> io::Result<Self> {
+        Self::new(MulticastSocket::with_options(
+            multicast_address,
+            interfaces,
+            options,
+        )?)
+    }
+
+    /// Access the underlying socket, for example to join or leave groups.
+    pub fn get_ref(&self) -> &MulticastSocket {
+        self.inner.get_ref()
+    }
+}
+
+impl AsyncMulticastSocket {
+    pub async fn receive(&self) -> io::Result<Message> {
+        loop {
+            let mut guard = self.inner.readable().await?;
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().receive()) {
+                return result;
+            }
+        }
+    }
+
+    pub async fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.writable().await?;
+            if let Ok(result) = guard.try_io(|inner| inner.get_ref().send(buf, interface)) {
+                return result;
+            }
+        }
+    }
+
+    /// See [`MulticastSocket::send_with`].
+    pub async fn send_with(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        options: &crate::SendOptions,
+    ) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.writable().await?;
+            if let Ok(result) =
+                guard.try_io(|inner| inner.get_ref().send_with(buf, interface, options))
+            {
+                return result;
+            }
+        }
+    }
+
+    pub async fn send_to(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        destination: &SocketAddrV4,
+    ) -> io::Result<usize> {
+        loop {
+            let mut guard = self.inner.writable().await?;
+            if let Ok(result) =
+                guard.try_io(|inner| inner.get_ref().send_to(buf, interface, destination))
+            {
+                return result;
+            }
+        }
+    }
+
+    /// See [`MulticastSocket::broadcast`].
+    pub async fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
+        match self.get_ref().broadcast_policy {
+            crate::BroadcastPolicy::FailFast => {
+                for interface in self.get_ref().joined_interfaces() {
+                    self.send(buf, &Interface::Ip(interface)).await?;
+                }
+                Ok(())
+            }
+            crate::BroadcastPolicy::BestEffort => {
+                crate::best_effort(self.broadcast_report(buf).await)
+            }
+        }
+    }
+
+    /// See [`MulticastSocket::broadcast_report`].
+    pub async fn broadcast_report(&self, buf: &[u8]) -> Vec<crate::BroadcastResult> {
+        let mut report = Vec::new();
+        for interface in self.get_ref().joined_interfaces() {
+            let interface = Interface::Ip(interface);
+            report.push(crate::BroadcastResult {
+                result: self.send(buf, &interface).await,
+                interface,
+            });
+        }
+        report
+    }
+}
@@ -0,0 +1,557 @@
+use std::io;
+use std::mem;
+use std::net::Ipv4Addr;
+use std::os::unix::io::AsRawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::{to_in_addr, Membership, MulticastSocket};
+
+/// How often the watcher thread checks if it was asked to stop.
+const STOP_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// The multicast group of IPv4 address notifications, from `linux/rtnetlink.h`, which libc
+/// does not define for Android.
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InterfaceChange {
+    /// The address was assigned, and the socket groups were joined through it, unless the
+    /// [`InterfaceFilter`](crate::InterfaceFilter) of the socket rejects it.
+    Added,
+    /// The address vanished, and the memberships joined through it were dropped.
+    Removed,
+    /// Notifications were lost, such as when the kernel queue overflowed with `ENOBUFS`, and
+    /// the memberships are brought back in line with the addresses of the host.
+    ///
+    /// `result` holds the error, and the differences found are reported as `Added` and
+    /// `Removed` events right after.
+    Resync,
+}
+
+#[derive(Debug)]
+pub struct InterfaceEvent {
+    pub change: InterfaceChange,
+    pub address: Ipv4Addr,
+    pub index: u32,
+    /// The outcome of updating the memberships for the change.
+    pub result: io::Result<()>,
+}
+
+/// Keeps the memberships of a [`MulticastSocket`] in sync with the addresses of the host.
+///
+/// Listens for `RTM_NEWADDR` and `RTM_DELADDR` rtnetlink notifications on a background thread,
+/// joining the socket groups on new IPv4 addresses and dropping the memberships of removed
+/// ones, so [`MulticastSocket::broadcast`] only uses addresses that still exist.
+///
+/// The thread stops when the watcher is dropped.
+pub struct InterfaceWatcher {
+    stop: Arc<AtomicBool>,
+    handle: Option<thread::JoinHandle<()>>,
+}
+
+impl InterfaceWatcher {
+    /// Starts watching, reporting every change handled to the callback.
+    pub fn spawn<F>(socket: Arc<MulticastSocket>, mut callback: F) -> io::Result<Self>
+    where
+        F: FnMut(InterfaceEvent) + Send + 'static,
+    {
+        let netlink = open_netlink()?;
+        let stop = Arc::new(AtomicBool::new(false));
+
+        // The index of every address, to drop memberships whose address vanished while
+        // notifications were lost
+        let mut known = current_addresses()?;
+
+        let thread_stop = stop.clone();
+        let handle = thread::Builder::new()
+            .name("multicast-interface-watcher".into())
+            .spawn(move || {
+                let mut buffer = vec![0u8; 8192];
+                while !thread_stop.load(Ordering::Relaxed) {
+                    let changes = match netlink.recv(&mut buffer) {
+                        Ok(read) => parse_address_changes(&buffer[..read]),
+                        // Timeouts allow checking the stop flag
+                        Err(e)
+                            if e.kind() == io::ErrorKind::WouldBlock
+                                || e.kind() == io::ErrorKind::TimedOut =>
+                        {
+                            continue
+                        }
+                        Err(e) => {
+                            callback(InterfaceEvent {
+                                change: InterfaceChange::Resync,
+                                address: Ipv4Addr::UNSPECIFIED,
+                                index: 0,
+                                result: Err(e),
+                            });
+                            match current_addresses() {
+                                Ok(current) => missed_changes(&known, &current),
+                                Err(e) => {
+                                    callback(InterfaceEvent {
+                                        change: InterfaceChange::Resync,
+                                        address: Ipv4Addr::UNSPECIFIED,
+                                        index: 0,
+                                        result: Err(e),
+                                    });
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    for (change, address, index) in changes {
+                        let result = match change {
+                            InterfaceChange::Added => {
+                                if !known.contains(&(address, index)) {
+                                    known.push((address, index));
+                                }
+                                socket.join_new_address(&address)
+                            }
+                            InterfaceChange::Removed => {
+                                known.retain(|known| *known != (address, index));
+                                socket.drop_address(&address, index)
+                            }
+                            InterfaceChange::Resync => Ok(()),
+                        };
+                        callback(InterfaceEvent {
+                            change,
+                            address,
+                            index,
+                            result,
+                        });
+                    }
+                }
+            })?;
+
+        Ok(InterfaceWatcher {
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Stops the watcher thread, waiting for it to finish.
+    pub fn stop(self) {
+        drop(self)
+    }
+}
+
+impl Drop for InterfaceWatcher {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn open_netlink() -> io::Result<Socket> {
+    let socket = Socket::new(
+        Domain::from(libc::AF_NETLINK),
+        Type::raw(),
+        Some(Protocol::from(libc::NETLINK_ROUTE)),
+    )?;
+    socket.set_read_timeout(Some(STOP_POLL_INTERVAL))?;
+
+    let mut address: libc::sockaddr_nl = unsafe { mem::zeroed() };
+    address.nl_family = libc::AF_NETLINK as _;
+    address.nl_groups = RTMGRP_IPV4_IFADDR;
+    let address = unsafe {
+        socket2::SockAddr::from_raw_parts(
+            &address as *const _ as *const _,
+            mem::size_of_val(&address) as _,
+        )
+    };
+    socket.bind(&address)?;
+
+    Ok(socket)
+}
+
+/// Lists the IPv4 addresses of the host with the index of their interface, leaving out the
+/// loopback ones like notifications do.
+fn current_addresses() -> io::Result<Vec<(Ipv4Addr, u32)>> {
+    Ok(crate::discover_interfaces()?
+        .into_iter()
+        .filter(|interface| !interface.loopback)
+        .flat_map(|interface| {
+            let index = interface.index;
+            interface
+                .addresses
+                .into_iter()
+                .map(move |address| (address.address, index))
+        })
+        .collect())
+}
+
+/// The changes between the addresses known to the watcher and the current ones.
+fn missed_changes(
+    known: &[(Ipv4Addr, u32)],
+    current: &[(Ipv4Addr, u32)],
+) -> Vec<(InterfaceChange, Ipv4Addr, u32)> {
+    let removed = known
+        .iter()
+        .filter(|address| !current.contains(address))
+        .map(|(address, index)| (InterfaceChange::Removed, *address, *index));
+    let added = current
+        .iter()
+        .filter(|address| !known.contains(address))
+        .map(|(address, index)| (InterfaceChange::Added, *address, *index));
+    removed.chain(added).collect()
+}
+
+// Layout of the rtnetlink messages, from `linux/netlink.h` and `linux/if_addr.h`
+const NLMSG_HEADER_SIZE: usize = 16;
+const IFADDRMSG_SIZE: usize = 8;
+const RTATTR_HEADER_SIZE: usize = 4;
+
+fn align(length: usize) -> usize {
+    (length + 3) & !3
+}
+
+fn read_u16(buffer: &[u8], at: usize) -> u16 {
+    u16::from_ne_bytes([buffer[at], buffer[at + 1]])
+}
+
+fn read_u32(buffer: &[u8], at: usize) -> u32 {
+    u32::from_ne_bytes([buffer[at], buffer[at + 1], buffer[at + 2], buffer[at + 3]])
+}
+
+fn parse_address_changes(buffer: &[u8]) -> Vec<(InterfaceChange, Ipv4Addr, u32)> {
+    let mut changes = Vec::new();
+
+    let mut offset = 0;
+    while offset + NLMSG_HEADER_SIZE <= buffer.len() {
+        let length = read_u32(buffer, offset) as usize;
+        if length < NLMSG_HEADER_SIZE || offset + length > buffer.len() {
+            break;
+        }
+        let message = &buffer[offset..offset + length];
+        offset += align(length);
+
+        let change = match read_u16(message, 4) {
+            libc::RTM_NEWADDR => InterfaceChange::Added,
+            libc::RTM_DELADDR => InterfaceChange::Removed,
+            _ => continue,
+        };
+
+        let payload = &message[NLMSG_HEADER_SIZE..];
+        if payload.len() < IFADDRMSG_SIZE {
+            continue;
+        }
+        let family = payload[0];
+        let scope = payload[3];
+        let index = read_u32(payload, 4);
+        // Loopback addresses have host scope, and are never joined
+        if family != libc::AF_INET as u8 || scope == libc::RT_SCOPE_HOST {
+            continue;
+        }
+
+        // IFA_LOCAL is the address of the interface itself, while IFA_ADDRESS is the peer
+        // address on point-to-point links
+        let mut local = None;
+        let mut address = None;
+        let mut attribute_offset = align(IFADDRMSG_SIZE);
+        while attribute_offset + RTATTR_HEADER_SIZE <= payload.len() {
+            let attribute_length = read_u16(payload, attribute_offset) as usize;
+            if attribute_length < RTATTR_HEADER_SIZE
+                || attribute_offset + attribute_length > payload.len()
+            {
+                break;
+            }
+            let data = &payload
+                [attribute_offset + RTATTR_HEADER_SIZE..attribute_offset + attribute_length];
+            if data.len() == 4 {
+                let ip = Ipv4Addr::new(data[0], data[1], data[2], data[3]);
+                match read_u16(payload, attribute_offset + 2) {
+                    libc::IFA_LOCAL => local = Some(ip),
+                    libc::IFA_ADDRESS => address = Some(ip),
+                    _ => {}
+                }
+            }
+            attribute_offset += align(attribute_length);
+        }
+
+        if let Some(ip) = local.or(address) {
+            changes.push((change, ip, index));
+        }
+    }
+
+    changes
+}
+
+impl MulticastSocket {
+    fn join_new_address(&self, address: &Ipv4Addr) -> io::Result<()> {
+        if let Some(filter) = &self.interface_filter {
+            let accepted = crate::InterfaceInfo::by_address(address)?
+                .map_or(false, |interface| filter.matches(&interface, address));
+            if !accepted {
+                return Ok(());
+            }
+        }
+
+        // The address gets the same groups and sources as the others
+        let mut joins: Vec<Membership> = Vec::new();
+        {
+            let memberships = self.memberships.lock().unwrap();
+            if memberships.iter().any(|m| m.interface == *address) {
+                return Ok(());
+            }
+            for membership in memberships.iter() {
+                let join = Membership {
+                    interface: *address,
+                    ..*membership
+                };
+                if !joins.contains(&join) {
+                    joins.push(join);
+                }
+            }
+        }
+        let group = *self.multicast_address.ip();
+        if !joins.iter().any(|m| m.group == group) {
+            joins.insert(
+                0,
+                Membership {
+                    group,
+                    interface: *address,
+                    source: None,
+                },
+            );
+        }
+
+        for join in &joins {
+            match self.join_membership(join) {
+                // The interface was already joined through another of its addresses, which
+                // source-specific joins report as `EADDRNOTAVAIL`
+                Err(e) if e.kind() == io::ErrorKind::AddrInUse => {}
+                Err(e) if join.source.is_some() && e.kind() == io::ErrorKind::AddrNotAvailable => {}
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    fn join_membership(&self, membership: &Membership) -> io::Result<()> {
+        match &membership.source {
+            Some(source) => {
+                self.join_source_group(&membership.group, source, &membership.interface)
+            }
+            None => self.join(&membership.group, &membership.interface),
+        }
+    }
+
+    fn drop_address(&self, address: &Ipv4Addr, index: u32) -> io::Result<()> {
+        let dropped: Vec<Membership> = {
+            let mut memberships = self.memberships.lock().unwrap();
+            let dropped = memberships
+                .iter()
+                .filter(|m| m.interface == *address)
+                .copied()
+                .collect();
+            memberships.retain(|m| m.interface != *address);
+            dropped
+        };
+        if dropped.is_empty() {
+            return Ok(());
+        }
+
+        // The address is already gone, so the kernel can only find the membership through the
+        // interface index. If the interface itself vanished, the kernel already dropped it.
+        let mut groups: Vec<Ipv4Addr> = Vec::with_capacity(dropped.len());
+        for membership in &dropped {
+            if !groups.contains(&membership.group) {
+                groups.push(membership.group);
+            }
+        }
+        for group in &groups {
+            let request = libc::ip_mreqn {
+                imr_multiaddr: to_in_addr(group),
+                imr_address: to_in_addr(&Ipv4Addr::UNSPECIFIED),
+                imr_ifindex: index as _,
+            };
+            unsafe {
+                libc::setsockopt(
+                    self.socket.as_raw_fd(),
+                    libc::IPPROTO_IP,
+                    libc::IP_DROP_MEMBERSHIP,
+                    &request as *const _ as *const libc::c_void,
+                    mem::size_of_val(&request) as libc::socklen_t,
+                )
+            };
+        }
+
+        // Keep the interface joined if it still has other addresses
+        let remaining = if_addrs::get_if_addrs()?
+            .into_iter()
+            .filter(|interface| interface.index == Some(index))
+            .find_map(|interface| match interface.ip() {
+                std::net::IpAddr::V4(v4) if v4 != *address => Some(v4),
+                _ => None,
+            });
+        if let Some(remaining) = remaining {
+            for membership in &dropped {
+                self.join_membership(&Membership {
+                    interface: remaining,
+                    ..*membership
+                })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn attribute(kind: u16, address: [u8; 4]) -> Vec<u8> {
+        let mut attribute = Vec::new();
+        attribute.extend_from_slice(&8u16.to_ne_bytes());
+        attribute.extend_from_slice(&kind.to_ne_bytes());
+        attribute.extend_from_slice(&address);
+        attribute
+    }
+
+    fn message(kind: u16, family: u8, scope: u8, index: u32, attributes: &[Vec<u8>]) -> Vec<u8> {
+        let mut payload = vec![family, 24, 0, scope];
+        payload.extend_from_slice(&index.to_ne_bytes());
+        for attribute in attributes {
+            payload.extend_from_slice(attribute);
+        }
+
+        let mut message = Vec::new();
+        let length = (NLMSG_HEADER_SIZE + payload.len()) as u32;
+        message.extend_from_slice(&length.to_ne_bytes());
+        message.extend_from_slice(&kind.to_ne_bytes());
+        message.extend_from_slice(&[0; 10]);
+        message.extend_from_slice(&payload);
+        message
+    }
+
+    fn address_message(kind: u16, address: [u8; 4], index: u32) -> Vec<u8> {
+        message(
+            kind,
+            libc::AF_INET as u8,
+            libc::RT_SCOPE_UNIVERSE,
+            index,
+            &[attribute(libc::IFA_LOCAL, address)],
+        )
+    }
+
+    #[test]
+    fn parses_new_address() {
+        let buffer = address_message(libc::RTM_NEWADDR, [192, 168, 1, 10], 3);
+        assert_eq!(
+            parse_address_changes(&buffer),
+            vec![(InterfaceChange::Added, Ipv4Addr::new(192, 168, 1, 10), 3)]
+        );
+    }
+
+    #[test]
+    fn parses_deleted_address() {
+        let buffer = address_message(libc::RTM_DELADDR, [10, 0, 0, 1], 7);
+        assert_eq!(
+            parse_address_changes(&buffer),
+            vec![(InterfaceChange::Removed, Ipv4Addr::new(10, 0, 0, 1), 7)]
+        );
+    }
+
+    #[test]
+    fn prefers_local_address_over_peer() {
+        let buffer = message(
+            libc::RTM_NEWADDR,
+            libc::AF_INET as u8,
+            libc::RT_SCOPE_UNIVERSE,
+            4,
+            &[
+                attribute(libc::IFA_ADDRESS, [10, 8, 0, 2]),
+                attribute(libc::IFA_LOCAL, [10, 8, 0, 1]),
+            ],
+        );
+        assert_eq!(
+            parse_address_changes(&buffer),
+            vec![(InterfaceChange::Added, Ipv4Addr::new(10, 8, 0, 1), 4)]
+        );
+    }
+
+    #[test]
+    fn parses_every_message_of_the_buffer() {
+        let mut buffer = address_message(libc::RTM_DELADDR, [10, 0, 0, 1], 2);
+        buffer.extend(address_message(libc::RTM_NEWADDR, [10, 0, 0, 2], 2));
+        assert_eq!(
+            parse_address_changes(&buffer),
+            vec![
+                (InterfaceChange::Removed, Ipv4Addr::new(10, 0, 0, 1), 2),
+                (InterfaceChange::Added, Ipv4Addr::new(10, 0, 0, 2), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_loopback_scope() {
+        let buffer = message(
+            libc::RTM_NEWADDR,
+            libc::AF_INET as u8,
+            libc::RT_SCOPE_HOST,
+            1,
+            &[attribute(libc::IFA_LOCAL, [127, 0, 0, 1])],
+        );
+        assert!(parse_address_changes(&buffer).is_empty());
+    }
+
+    #[test]
+    fn skips_other_families_and_messages() {
+        let mut buffer = message(
+            libc::RTM_NEWADDR,
+            libc::AF_INET6 as u8,
+            libc::RT_SCOPE_UNIVERSE,
+            2,
+            &[],
+        );
+        buffer.extend(address_message(libc::RTM_NEWLINK, [10, 0, 0, 1], 2));
+        assert!(parse_address_changes(&buffer).is_empty());
+    }
+
+    #[test]
+    fn stops_at_truncated_message() {
+        let mut buffer = address_message(libc::RTM_NEWADDR, [10, 0, 0, 1], 2);
+        let second = address_message(libc::RTM_NEWADDR, [10, 0, 0, 2], 2);
+        buffer.extend_from_slice(&second[..second.len() - 4]);
+        assert_eq!(
+            parse_address_changes(&buffer),
+            vec![(InterfaceChange::Added, Ipv4Addr::new(10, 0, 0, 1), 2)]
+        );
+
+        assert!(parse_address_changes(&buffer[..NLMSG_HEADER_SIZE - 1]).is_empty());
+    }
+
+    #[test]
+    fn ignores_truncated_attribute() {
+        let mut buffer = address_message(libc::RTM_NEWADDR, [10, 0, 0, 1], 2);
+        // Claims more data than the message holds
+        let at = NLMSG_HEADER_SIZE + IFADDRMSG_SIZE;
+        buffer[at..at + 2].copy_from_slice(&12u16.to_ne_bytes());
+        assert!(parse_address_changes(&buffer).is_empty());
+    }
+
+    #[test]
+    fn finds_missed_changes() {
+        let known = [
+            (Ipv4Addr::new(10, 0, 0, 1), 2),
+            (Ipv4Addr::new(10, 0, 0, 2), 3),
+        ];
+        let current = [
+            (Ipv4Addr::new(10, 0, 0, 2), 3),
+            (Ipv4Addr::new(10, 0, 0, 3), 4),
+        ];
+        assert_eq!(
+            missed_changes(&known, &current),
+            vec![
+                (InterfaceChange::Removed, Ipv4Addr::new(10, 0, 0, 1), 2),
+                (InterfaceChange::Added, Ipv4Addr::new(10, 0, 0, 3), 4),
+            ]
+        );
+    }
+}
@@ -7,14 +7,18 @@ use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::os::windows::prelude::*;
 use std::ptr;
 use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::SystemTime;
 
 use socket2::{Domain, Protocol, Socket, Type};
 
+use crate::error::{Context, Operation};
+
 use winapi::ctypes::{c_char, c_int};
 use winapi::shared::inaddr::*;
 use winapi::shared::minwindef::DWORD;
 use winapi::shared::minwindef::{INT, LPDWORD};
-use winapi::shared::winerror::ERROR_BUFFER_OVERFLOW;
+use winapi::shared::winerror::{ERROR_BUFFER_OVERFLOW, WSAEMSGSIZE};
 use winapi::shared::ws2def::LPWSAMSG;
 use winapi::shared::ws2def::*;
 use winapi::shared::ws2ipdef::*;
@@ -136,42 +140,201 @@ fn set_pktinfo(socket: RawSocket, payload: bool) -> io::Result<()> {
     unsafe { setsockopt(socket, IPPROTO_IP, IP_PKTINFO, payload as c_int) }
 }
 
-fn create_on_interfaces(
+fn set_source_membership(
+    socket: RawSocket,
+    option: c_int,
+    group: &Ipv4Addr,
+    source: &Ipv4Addr,
+    interface: &Ipv4Addr,
+) -> io::Result<()> {
+    let request = IP_MREQ_SOURCE {
+        imr_multiaddr: IN_ADDR {
+            S_un: to_s_addr(group),
+        },
+        imr_sourceaddr: IN_ADDR {
+            S_un: to_s_addr(source),
+        },
+        imr_interface: IN_ADDR {
+            S_un: to_s_addr(interface),
+        },
+    };
+    unsafe { setsockopt(socket, IPPROTO_IP, option, request) }
+}
+
+pub(crate) fn create_on_interfaces(
     options: crate::MulticastOptions,
     interfaces: Vec<Ipv4Addr>,
     multicast_address: SocketAddrV4,
-) -> io::Result<MulticastSocket> {
-    let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))?;
-    socket.set_read_timeout(options.read_timeout)?;
-    socket.set_multicast_loop_v4(options.loopback)?;
-    socket.set_reuse_address(true)?;
+) -> Result<MulticastSocket, crate::Error> {
+    let interfaces = crate::filter_interfaces(options.interface_filter.as_ref(), interfaces)?;
+
+    let socket = Socket::new(Domain::ipv4(), Type::dgram(), Some(Protocol::udp()))
+        .context(Operation::CreateSocket)?;
+    socket
+        .set_read_timeout(options.read_timeout)
+        .context(Operation::SetOption("SO_RCVTIMEO"))?;
+    socket
+        .set_multicast_loop_v4(options.loopback)
+        .context(Operation::SetOption("IP_MULTICAST_LOOP"))?;
+    if let Some(ttl) = options.ttl {
+        socket
+            .set_multicast_ttl_v4(ttl)
+            .context(Operation::SetOption("IP_MULTICAST_TTL"))?;
+    }
+    if let Some(tos) = options.tos {
+        unsafe { setsockopt(socket.as_raw_socket(), IPPROTO_IP, IP_TOS, tos as c_int) }
+            .context(Operation::SetOption("IP_TOS"))?;
+    }
+    if options.priority.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "SO_PRIORITY is only supported on Linux",
+        ))
+        .context(Operation::SetOption("SO_PRIORITY"));
+    }
+    if options.min_ttl.is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "received TTLs are only reported on Linux",
+        ))
+        .context(Operation::SetOption("IP_RECVTTL"));
+    }
+    if options.timestamping != crate::Timestamping::Off {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "receive timestamps are only supported on Linux",
+        ))
+        .context(Operation::SetOption("SO_TIMESTAMPING"));
+    }
+    socket
+        .set_reuse_address(options.reuse_address)
+        .context(Operation::SetOption("SO_REUSEADDR"))?;
+    if let Some(size) = options.receive_buffer_size {
+        socket
+            .set_recv_buffer_size(size)
+            .context(Operation::SetOption("SO_RCVBUF"))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket
+            .set_send_buffer_size(size)
+            .context(Operation::SetOption("SO_SNDBUF"))?;
+    }
 
     // enable fetching interface information and locate the extension function
-    set_pktinfo(socket.as_raw_socket(), true)?;
-    let wsarecvmsg: WSARecvMsgExtension = locate_wsarecvmsg(socket.as_raw_socket())?;
-    let wsasendmsg: WSASendMsgExtension = locate_wsasendmsg(socket.as_raw_socket())?;
+    set_pktinfo(socket.as_raw_socket(), true).context(Operation::SetOption("IP_PKTINFO"))?;
+    let wsarecvmsg: WSARecvMsgExtension =
+        locate_wsarecvmsg(socket.as_raw_socket()).context(Operation::CreateSocket)?;
+    let wsasendmsg: WSASendMsgExtension =
+        locate_wsasendmsg(socket.as_raw_socket()).context(Operation::CreateSocket)?;
 
     // Join multicast listeners on every interface passed
-    for interface in &interfaces {
-        socket.join_multicast_v4(multicast_address.ip(), &interface)?;
+    let mut joined = Vec::with_capacity(interfaces.len());
+    let mut skipped = Vec::new();
+    for interface in interfaces {
+        let result = join_interface(
+            &socket,
+            multicast_address.ip(),
+            &options.sources,
+            &interface,
+        )
+        .context_on(Operation::Join, &Interface::Ip(interface));
+        match result {
+            Ok(()) => joined.push(interface),
+            Err(error) if options.skip_failing_interfaces => {
+                skipped.push(crate::SkippedInterface {
+                    interface: Interface::Ip(interface),
+                    error,
+                })
+            }
+            Err(error) => return Err(error),
+        }
+    }
+    if joined.is_empty() && !skipped.is_empty() {
+        return Err(skipped.remove(0).error);
     }
 
     // On Windows, unlike all Unix variants, it is improper to bind to the multicast address
     // see https://msdn.microsoft.com/en-us/library/windows/desktop/ms737550(v=vs.85).aspx
-    socket.bind(&SocketAddr::new(options.bind_address.into(), multicast_address.port()).into())?;
+    socket
+        .bind(&SocketAddr::new(options.bind_address.into(), multicast_address.port()).into())
+        .context(Operation::Bind)?;
 
-    let interfaces = build_address_table(HashSet::from_iter(interfaces))?;
+    let memberships = joined
+        .iter()
+        .flat_map(|interface| memberships_on(multicast_address.ip(), &options.sources, interface))
+        .collect();
+    let interfaces =
+        build_address_table(HashSet::from_iter(joined)).context(Operation::DiscoverInterfaces)?;
 
     Ok(MulticastSocket {
         socket,
         wsarecvmsg,
         wsasendmsg,
-        interfaces,
+        memberships: Mutex::new(memberships),
+        interfaces: Mutex::new(interfaces),
         multicast_address,
         buffer_size: options.buffer_size,
+        broadcast_policy: options.broadcast_policy,
+        skipped,
+        sources: options.sources,
     })
 }
 
+/// Joins the group on the interface, for any source or only for the listed ones.
+fn join_interface(
+    socket: &Socket,
+    group: &Ipv4Addr,
+    sources: &[Ipv4Addr],
+    interface: &Ipv4Addr,
+) -> io::Result<()> {
+    if sources.is_empty() {
+        return socket.join_multicast_v4(group, interface);
+    }
+
+    for (joined, source) in sources.iter().enumerate() {
+        let result = set_source_membership(
+            socket.as_raw_socket(),
+            IP_ADD_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
+        );
+        if let Err(e) = result {
+            // Leave the sources already joined, so a skipped interface is not half joined
+            for source in &sources[..joined] {
+                let _ = set_source_membership(
+                    socket.as_raw_socket(),
+                    IP_DROP_SOURCE_MEMBERSHIP,
+                    group,
+                    source,
+                    interface,
+                );
+            }
+            return Err(e);
+        }
+    }
+    Ok(())
+}
+
+/// The memberships added by [`join_interface`], one per source.
+fn memberships_on(group: &Ipv4Addr, sources: &[Ipv4Addr], interface: &Ipv4Addr) -> Vec<Membership> {
+    if sources.is_empty() {
+        return vec![Membership {
+            group: *group,
+            interface: *interface,
+            source: None,
+        }];
+    }
+    sources
+        .iter()
+        .map(|source| Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        })
+        .collect()
+}
+
 fn build_address_table(interfaces: HashSet<Ipv4Addr>) -> io::Result<HashMap<u32, Ipv4Addr>> {
     let mut size = 0u32;
     let r = unsafe { winapi::um::iphlpapi::GetAdaptersInfo(ptr::null_mut(), &mut size) };
@@ -224,9 +387,22 @@ pub struct MulticastSocket {
     socket: socket2::Socket,
     wsarecvmsg: WSARecvMsgExtension,
     wsasendmsg: WSASendMsgExtension,
-    interfaces: HashMap<u32, Ipv4Addr>,
+    memberships: Mutex<Vec<Membership>>,
+    interfaces: Mutex<HashMap<u32, Ipv4Addr>>,
     multicast_address: SocketAddrV4,
     buffer_size: usize,
+    broadcast_policy: crate::BroadcastPolicy,
+    skipped: Vec<crate::SkippedInterface>,
+    // Kept so the group of the socket is always joined with the same sources
+    sources: Vec<Ipv4Addr>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Membership {
+    group: Ipv4Addr,
+    interface: Ipv4Addr,
+    /// The only sender accepted, for source-specific joins.
+    source: Option<Ipv4Addr>,
 }
 
 #[derive(Debug, Clone)]
@@ -234,6 +410,28 @@ pub enum Interface {
     Default,
     Ip(Ipv4Addr),
     Index(u32),
+    /// An interface name, as listed by [`discover_interfaces`], resolved on every send.
+    Name(String),
+}
+
+impl Interface {
+    /// Resolves the name of the interface, as listed by [`discover_interfaces`].
+    pub fn name(&self) -> io::Result<String> {
+        let found = match self {
+            Interface::Name(name) => return Ok(name.clone()),
+            Interface::Index(index) => crate::InterfaceInfo::by_index(*index)?,
+            Interface::Ip(address) => crate::InterfaceInfo::by_address(address)?,
+            Interface::Default => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the default interface has no name",
+                ))
+            }
+        };
+        found
+            .map(|interface| interface.name)
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such interface"))
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -241,6 +439,77 @@ pub struct Message {
     pub data: Vec<u8>,
     pub origin_address: SocketAddrV4,
     pub interface: Interface,
+    /// The address the packet was sent to, identifying which of the joined groups it belongs
+    /// to, or the address of this host for unicast packets.
+    pub destination_address: Ipv4Addr,
+    /// The local address of the interface the packet was received on.
+    ///
+    /// Windows only reports the interface index, so the address is only known for the
+    /// interfaces the socket has joined, and is `UNSPECIFIED` otherwise.
+    pub interface_address: Ipv4Addr,
+    /// Whether the packet was larger than the receive buffer, and its data was cut short.
+    ///
+    /// Increase [`MulticastOptions::buffer_size`](crate::MulticastOptions::buffer_size) when
+    /// this happens.
+    pub truncated: bool,
+    /// The type of service byte of the packet, which Windows does not report.
+    pub tos: Option<u8>,
+    /// The TTL the packet arrived with, which Windows does not report.
+    pub ttl: Option<u8>,
+    /// When the packet arrived, which Windows does not report.
+    pub received_at: Option<SystemTime>,
+}
+
+impl Message {
+    /// Whether the packet was sent to a multicast group, instead of directly to this host.
+    pub fn is_multicast(&self) -> bool {
+        self.destination_address.is_multicast()
+    }
+
+    /// Resolves the name of the interface the packet was received on.
+    pub fn interface_name(&self) -> io::Result<String> {
+        self.interface.name()
+    }
+
+    /// The DSCP code point, from the upper six bits of [`Message::tos`].
+    pub fn dscp(&self) -> Option<u8> {
+        self.tos.map(|tos| tos >> 2)
+    }
+
+    fn from_meta(data: Vec<u8>, meta: MessageMeta) -> Self {
+        Message {
+            data,
+            origin_address: meta.origin_address,
+            interface: meta.interface,
+            destination_address: meta.destination_address,
+            interface_address: meta.interface_address,
+            truncated: meta.truncated,
+            tos: meta.tos,
+            ttl: meta.ttl,
+            received_at: meta.received_at,
+        }
+    }
+}
+
+/// The details of a packet received with [`MulticastSocket::receive_into`].
+#[derive(Debug, Clone)]
+pub struct MessageMeta {
+    /// How many bytes of the buffer were filled with the packet data.
+    pub len: usize,
+    pub origin_address: SocketAddrV4,
+    pub interface: Interface,
+    /// See [`Message::destination_address`].
+    pub destination_address: Ipv4Addr,
+    /// See [`Message::interface_address`].
+    pub interface_address: Ipv4Addr,
+    /// See [`Message::truncated`].
+    pub truncated: bool,
+    /// See [`Message::tos`].
+    pub tos: Option<u8>,
+    /// See [`Message::ttl`].
+    pub ttl: Option<u8>,
+    /// See [`Message::received_at`].
+    pub received_at: Option<SystemTime>,
 }
 
 const CMSG_HEADER_SIZE: usize = mem::size_of::<WSACMSGHDR>();
@@ -258,10 +527,48 @@ pub fn all_ipv4_interfaces() -> io::Result<Vec<Ipv4Addr>> {
     Ok(interfaces)
 }
 
+/// Lists every interface of the host with an IPv4 address.
+///
+/// Windows does not expose the same interface flags as unix platforms, so every listed
+/// interface is reported as up, running and multicast capable, and never as point-to-point.
+pub fn discover_interfaces() -> io::Result<Vec<crate::InterfaceInfo>> {
+    let mut interfaces: Vec<crate::InterfaceInfo> = Vec::new();
+    for interface in if_addrs::get_if_addrs()? {
+        let loopback = interface.is_loopback();
+        let address = match &interface.addr {
+            if_addrs::IfAddr::V4(v4) => crate::InterfaceAddress {
+                address: v4.ip,
+                netmask: v4.netmask,
+                broadcast: v4.broadcast,
+            },
+            _ => continue,
+        };
+
+        match interfaces.iter_mut().find(|i| i.name == interface.name) {
+            Some(known) => known.addresses.push(address),
+            None => interfaces.push(crate::InterfaceInfo {
+                name: interface.name,
+                index: interface.index.unwrap_or_default(),
+                addresses: vec![address],
+                up: true,
+                running: true,
+                multicast: true,
+                loopback,
+                point_to_point: false,
+            }),
+        }
+    }
+    Ok(interfaces)
+}
+
 impl MulticastSocket {
     pub fn all_interfaces(multicast_address: SocketAddrV4) -> io::Result<Self> {
-        let interfaces = all_ipv4_interfaces()?;
-        create_on_interfaces(Default::default(), interfaces, multicast_address)
+        let interfaces = all_ipv4_interfaces().context(Operation::DiscoverInterfaces)?;
+        Ok(create_on_interfaces(
+            Default::default(),
+            interfaces,
+            multicast_address,
+        )?)
     }
 
     pub fn with_options(
@@ -269,16 +576,29 @@ impl MulticastSocket {
         interfaces: Vec<Ipv4Addr>,
         options: crate::MulticastOptions,
     ) -> io::Result<Self> {
-        create_on_interfaces(options, interfaces, multicast_address)
+        Ok(create_on_interfaces(
+            options,
+            interfaces,
+            multicast_address,
+        )?)
     }
 }
 
 impl MulticastSocket {
     pub fn receive(&self) -> io::Result<Message> {
-        let mut data_buffer = vec![0; self.buffer_size];
+        let mut data = vec![0; self.buffer_size];
+        let meta = self.receive_into(&mut data)?;
+        data.truncate(meta.len);
+        Ok(Message::from_meta(data, meta))
+    }
+
+    /// Receives a packet into the buffer, without allocating.
+    ///
+    /// The packet data is written to `buf[..meta.len]`.
+    pub fn receive_into(&self, buf: &mut [u8]) -> io::Result<MessageMeta> {
         let mut data = WSABUF {
-            buf: data_buffer.as_mut_ptr(),
-            len: data_buffer.len() as u32,
+            buf: buf.as_mut_ptr() as *mut _,
+            len: buf.len() as u32,
         };
 
         let mut control_buffer = [0; CONTROL_PKTINFO_BUFFER_SIZE];
@@ -310,7 +630,10 @@ impl MulticastSocket {
             }
         };
 
-        if r != 0 {
+        // Datagrams larger than the buffer are reported as an error, but the buffer is still
+        // filled with the beginning of the packet
+        let truncated = r != 0 && unsafe { sock::WSAGetLastError() } == WSAEMSGSIZE as i32;
+        if r != 0 && !truncated {
             return Err(io::Error::last_os_error());
         }
 
@@ -328,6 +651,8 @@ impl MulticastSocket {
         };
 
         let mut interface = Interface::Default;
+        let mut destination_address = Ipv4Addr::UNSPECIFIED;
+        let mut interface_address = Ipv4Addr::UNSPECIFIED;
         // Ensures that the control buffer is the size of the CSMG_HEADER + the pkinto data
         if control.len as usize == CONTROL_PKTINFO_BUFFER_SIZE {
             let cmsg_header: WSACMSGHDR = unsafe { ptr::read_unaligned(control.buf as *const _) }; // TODO fix clippy warning without breaking the code
@@ -335,20 +660,75 @@ impl MulticastSocket {
                 let interface_info: IN_PKTINFO =
                     unsafe { ptr::read_unaligned(control.buf.add(CMSG_HEADER_SIZE) as *const _) }; // TODO fix clippy warning without breaking the code
                 interface = Interface::Index(interface_info.ipi_ifindex);
+                destination_address = from_s_addr(&interface_info.ipi_addr.S_un);
+                if let Some(address) = self
+                    .interfaces
+                    .lock()
+                    .unwrap()
+                    .get(&interface_info.ipi_ifindex)
+                {
+                    interface_address = *address;
+                }
             };
         };
 
-        Ok(Message {
-            data: data_buffer[0..read_bytes as _]
-                .iter()
-                .map(|i| *i as u8)
-                .collect(),
+        Ok(MessageMeta {
+            len: read_bytes as _,
             origin_address,
             interface,
+            destination_address,
+            interface_address,
+            truncated: truncated || wsa_msg.dwFlags & MSG_TRUNC as DWORD != 0,
+            tos: None,
+            ttl: None,
+            received_at: None,
         })
     }
 
+    /// Sends the buffer to the multicast group the socket was created with.
     pub fn send(&self, buf: &[u8], interface: &Interface) -> io::Result<usize> {
+        self.send_to(buf, interface, &self.multicast_address)
+    }
+
+    /// Sends the buffer to another destination, such as any of the groups joined with
+    /// [`MulticastSocket::join`].
+    pub fn send_to(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        destination: &SocketAddrV4,
+    ) -> io::Result<usize> {
+        self.send_to_with(buf, interface, destination, &crate::SendOptions::default())
+    }
+
+    /// Sends the buffer to the multicast group, overriding socket options for this packet.
+    pub fn send_with(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        options: &crate::SendOptions,
+    ) -> io::Result<usize> {
+        self.send_to_with(buf, interface, &self.multicast_address, options)
+    }
+
+    /// Sends the buffer to the destination, overriding socket options for this packet.
+    ///
+    /// Per-packet options are only supported on Linux, so setting any of them fails with
+    /// [`io::ErrorKind::Unsupported`].
+    pub fn send_to_with(
+        &self,
+        buf: &[u8],
+        interface: &Interface,
+        destination: &SocketAddrV4,
+        options: &crate::SendOptions,
+    ) -> io::Result<usize> {
+        if options.ttl.is_some() || options.tos.is_some() {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "per-packet options are only supported on Linux",
+            ));
+        }
+
         let pkt_info = match interface {
             Interface::Default => None,
             Interface::Ip(address) => Some(IN_PKTINFO {
@@ -357,12 +737,38 @@ impl MulticastSocket {
                 },
                 ipi_ifindex: 0,
             }),
-            Interface::Index(index) => self.interfaces.get(index).map(|address| IN_PKTINFO {
-                ipi_addr: IN_ADDR {
-                    S_un: to_s_addr(address),
-                },
-                ipi_ifindex: *index,
-            }),
+            Interface::Index(index) => {
+                self.interfaces
+                    .lock()
+                    .unwrap()
+                    .get(index)
+                    .map(|address| IN_PKTINFO {
+                        ipi_addr: IN_ADDR {
+                            S_un: to_s_addr(address),
+                        },
+                        ipi_ifindex: *index,
+                    })
+            }
+            Interface::Name(name) => {
+                let found = crate::InterfaceInfo::by_name(name)
+                    .io_context_on(Operation::Send, interface)?
+                    .and_then(|info| Some((info.index, info.ipv4()?)));
+                match found {
+                    Some((index, address)) => Some(IN_PKTINFO {
+                        ipi_addr: IN_ADDR {
+                            S_un: to_s_addr(&address),
+                        },
+                        ipi_ifindex: index,
+                    }),
+                    None => {
+                        return Err(io::Error::new(
+                            io::ErrorKind::NotFound,
+                            format!("interface {} has no IPv4 address", name),
+                        ))
+                        .io_context_on(Operation::Send, interface)
+                    }
+                }
+            }
         };
 
         let mut data = WSABUF {
@@ -400,7 +806,7 @@ impl MulticastSocket {
             }
         };
 
-        let destination = socket2::SockAddr::from(self.multicast_address);
+        let destination = socket2::SockAddr::from(*destination);
         let destination_address = destination.as_ptr();
         let mut wsa_msg = WSAMSG {
             name: destination_address as *mut _,
@@ -423,18 +829,242 @@ impl MulticastSocket {
             )
         };
         if r != 0 {
-            return Err(io::Error::last_os_error());
+            return Err(io::Error::last_os_error()).io_context_on(Operation::Send, interface);
         }
 
         Ok(sent_bytes as _)
     }
 
+    /// Sends the buffer on every interface with at least one joined group.
+    ///
+    /// Failing interfaces are handled according to
+    /// [`MulticastOptions::broadcast_policy`](crate::MulticastOptions::broadcast_policy).
     pub fn broadcast(&self, buf: &[u8]) -> io::Result<()> {
-        for interface in self.interfaces.values() {
-            self.send(buf, &Interface::Ip(*interface))?;
+        match self.broadcast_policy {
+            crate::BroadcastPolicy::FailFast => {
+                for interface in self.joined_interfaces() {
+                    self.send(buf, &Interface::Ip(interface))?;
+                }
+                Ok(())
+            }
+            crate::BroadcastPolicy::BestEffort => crate::best_effort(self.broadcast_report(buf)),
         }
+    }
+
+    /// Sends the buffer on every interface with at least one joined group, reporting the
+    /// outcome of each one, regardless of the broadcast policy.
+    pub fn broadcast_report(&self, buf: &[u8]) -> Vec<crate::BroadcastResult> {
+        self.joined_interfaces()
+            .into_iter()
+            .map(|interface| {
+                let interface = Interface::Ip(interface);
+                crate::BroadcastResult {
+                    result: self.send(buf, &interface),
+                    interface,
+                }
+            })
+            .collect()
+    }
+
+    /// The size of the kernel receive queue, as granted for
+    /// [`MulticastOptions::receive_buffer_size`](crate::MulticastOptions::receive_buffer_size).
+    pub fn receive_buffer_size(&self) -> io::Result<usize> {
+        self.socket.recv_buffer_size()
+    }
+
+    /// The size of the kernel send queue, as granted for
+    /// [`MulticastOptions::send_buffer_size`](crate::MulticastOptions::send_buffer_size).
+    pub fn send_buffer_size(&self) -> io::Result<usize> {
+        self.socket.send_buffer_size()
+    }
+
+    /// The interfaces with at least one joined group, used by [`MulticastSocket::broadcast`].
+    pub fn active_interfaces(&self) -> Vec<Ipv4Addr> {
+        self.joined_interfaces()
+    }
+
+    /// The interfaces left out when the socket was created, because joining the group on them
+    /// failed.
+    ///
+    /// Only filled when
+    /// [`MulticastOptions::skip_failing_interfaces`](crate::MulticastOptions::skip_failing_interfaces)
+    /// is enabled.
+    pub fn skipped_interfaces(&self) -> &[crate::SkippedInterface] {
+        &self.skipped
+    }
+
+    fn joined_interfaces(&self) -> Vec<Ipv4Addr> {
+        // Copied out, so the table is not locked while sending
+        self.interfaces.lock().unwrap().values().copied().collect()
+    }
+}
+
+impl MulticastSocket {
+    /// Toggles the non-blocking mode, where [`MulticastSocket::receive`] and the send methods
+    /// return [`io::ErrorKind::WouldBlock`] instead of waiting.
+    pub fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.socket.set_nonblocking(nonblocking)
+    }
+}
+
+impl MulticastSocket {
+    /// Joins the group on the interface, without rebinding the socket.
+    ///
+    /// The interface is included on the next [`MulticastSocket::broadcast`] calls. Packets of
+    /// every joined group are received by the same socket, as long as they are sent to the
+    /// port it is bound to, and can be told apart by [`Message::destination_address`].
+    ///
+    /// The group of the socket is only joined for the
+    /// [`MulticastOptions::sources`](crate::MulticastOptions::sources), when any are listed.
+    pub fn join(&self, group: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        let sources: &[Ipv4Addr] = if group == self.multicast_address.ip() {
+            &self.sources
+        } else {
+            &[]
+        };
+        let mut memberships = self.memberships.lock().unwrap();
+        join_interface(&self.socket, group, sources, interface)
+            .io_context_on(Operation::Join, &Interface::Ip(*interface))?;
+        self.add_interface(interface)?;
+        memberships.extend(memberships_on(group, sources, interface));
         Ok(())
     }
+
+    /// Leaves the group on the interface, without rebinding the socket.
+    ///
+    /// Every source joined on the interface for the group is left as well. Once the interface
+    /// has no joined groups left, it is no longer used by [`MulticastSocket::broadcast`].
+    pub fn leave(&self, group: &Ipv4Addr, interface: &Ipv4Addr) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        self.socket
+            .leave_multicast_v4(group, interface)
+            .io_context_on(Operation::Leave, &Interface::Ip(*interface))?;
+        memberships.retain(|m| m.group != *group || m.interface != *interface);
+        self.remove_interface(&memberships, interface);
+        Ok(())
+    }
+
+    /// Adds the interface to the table used by [`MulticastSocket::broadcast`].
+    fn add_interface(&self, interface: &Ipv4Addr) -> io::Result<()> {
+        let mut interfaces = self.interfaces.lock().unwrap();
+        if !interfaces.values().any(|known| known == interface) {
+            let mut new_interface = HashSet::with_capacity(1);
+            new_interface.insert(*interface);
+            interfaces.extend(build_address_table(new_interface)?);
+        }
+        Ok(())
+    }
+
+    /// Removes the interface from the table used by [`MulticastSocket::broadcast`], once it
+    /// has no joined groups left.
+    fn remove_interface(&self, memberships: &[Membership], interface: &Ipv4Addr) {
+        if !memberships.iter().any(|m| m.interface == *interface) {
+            self.interfaces
+                .lock()
+                .unwrap()
+                .retain(|_, known| known != interface);
+        }
+    }
+}
+
+impl MulticastSocket {
+    /// Joins the group on the interface, accepting only packets sent by `source`.
+    ///
+    /// Translates to `IP_ADD_SOURCE_MEMBERSHIP`, and can be called multiple times to accept
+    /// multiple senders. Like [`MulticastSocket::join`], the interface is included on the next
+    /// [`MulticastSocket::broadcast`] calls.
+    pub fn join_source_group(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        set_source_membership(
+            self.socket.as_raw_socket(),
+            IP_ADD_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(Operation::Join, &Interface::Ip(*interface))?;
+        self.add_interface(interface)?;
+        memberships.push(Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        });
+        Ok(())
+    }
+
+    /// Stops accepting packets from `source`, undoing [`MulticastSocket::join_source_group`].
+    pub fn leave_source_group(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        let mut memberships = self.memberships.lock().unwrap();
+        set_source_membership(
+            self.socket.as_raw_socket(),
+            IP_DROP_SOURCE_MEMBERSHIP,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(Operation::Leave, &Interface::Ip(*interface))?;
+        let membership = Membership {
+            group: *group,
+            interface: *interface,
+            source: Some(*source),
+        };
+        memberships.retain(|m| *m != membership);
+        self.remove_interface(&memberships, interface);
+        Ok(())
+    }
+
+    /// Ignores packets sent by `source` on a group joined for any source.
+    ///
+    /// Translates to `IP_BLOCK_SOURCE`, which requires the group to already be joined on the
+    /// interface without source filters.
+    pub fn block_source(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        set_source_membership(
+            self.socket.as_raw_socket(),
+            IP_BLOCK_SOURCE,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(
+            Operation::SetOption("IP_BLOCK_SOURCE"),
+            &Interface::Ip(*interface),
+        )
+    }
+
+    /// Accepts packets from `source` again, undoing [`MulticastSocket::block_source`].
+    pub fn unblock_source(
+        &self,
+        group: &Ipv4Addr,
+        source: &Ipv4Addr,
+        interface: &Ipv4Addr,
+    ) -> io::Result<()> {
+        set_source_membership(
+            self.socket.as_raw_socket(),
+            IP_UNBLOCK_SOURCE,
+            group,
+            source,
+            interface,
+        )
+        .io_context_on(
+            Operation::SetOption("IP_UNBLOCK_SOURCE"),
+            &Interface::Ip(*interface),
+        )
+    }
 }
 
 fn to_s_addr(addr: &Ipv4Addr) -> in_addr_S_un {
@@ -444,3 +1074,7 @@ fn to_s_addr(addr: &Ipv4Addr) -> in_addr_S_un {
     unsafe { *(new_addr.S_addr_mut()) = res };
     new_addr
 }
+
+fn from_s_addr(addr: &in_addr_S_un) -> Ipv4Addr {
+    Ipv4Addr::from(unsafe { *addr.S_addr() }.to_ne_bytes())
+}